@@ -0,0 +1,388 @@
+use crate::{Body, Face, Frame, PageElement, Text};
+
+// Axis aligned rectangle in absolute (pixel) image coordinates, origin at the upper-left corner of
+// the page, same as xmin/ymin/xmax/ymax in the XML.
+// Formats used by the other tools:
+//  - xyxy: (xmin, ymin, xmax, ymax), what Manga109 (and Pascal VOC) uses
+//  - xywh: (center_x, center_y, width, height), what YOLO uses (normalized to [0.0 .. 1.0])
+//  - ltwh: (left, top, width, height), what COCO uses
+// The normalized variants divide x and width by the image width, y and height by the image height;
+// it does not matter whether that is the original or the resized image, as long as it is the one
+// the box is in.  They return None when the image width or height is 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundingBox {
+    pub xmin: u32,
+    pub ymin: u32,
+    pub xmax: u32,
+    pub ymax: u32,
+}
+
+// Anything with a bounding box, i.e. the Frame, Text, Face and Body of a page
+pub trait HasBoundingBox {
+    fn bounding_box(&self) -> BoundingBox;
+}
+
+// None for an image without area (i.e. a <page width="0">), there is nothing to normalize against
+fn normalized_dimensions(image_width: u32, image_height: u32) -> Option<(f32, f32)> {
+    if image_width == 0 || image_height == 0 {
+        return None;
+    }
+    Some((image_width as f32, image_height as f32))
+}
+
+// rounds to the nearest pixel, negative values end up as 0
+fn to_pixel(value: f32) -> u32 {
+    value.round().max(0.0) as u32
+}
+
+impl BoundingBox {
+    // Inverted corners (i.e. xmin > xmax) are swapped, so width() and height() never underflow
+    pub fn new(xmin: u32, ymin: u32, xmax: u32, ymax: u32) -> Self {
+        BoundingBox {
+            xmin: xmin.min(xmax),
+            ymin: ymin.min(ymax),
+            xmax: xmin.max(xmax),
+            ymax: ymin.max(ymax),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.xmax.saturating_sub(self.xmin)
+    }
+    pub fn height(&self) -> u32 {
+        self.ymax.saturating_sub(self.ymin)
+    }
+    pub fn area(&self) -> u64 {
+        self.width() as u64 * self.height() as u64
+    }
+    pub fn is_empty(&self) -> bool {
+        self.area() == 0
+    }
+    pub fn center(&self) -> (f32, f32) {
+        (
+            (self.xmin as f32 + self.xmax as f32) / 2.0,
+            (self.ymin as f32 + self.ymax as f32) / 2.0,
+        )
+    }
+
+    // The overlapping part of both boxes, None if they do not overlap (touching edges do not count)
+    pub fn intersection(&self, other: &BoundingBox) -> Option<BoundingBox> {
+        let intersection = BoundingBox {
+            xmin: self.xmin.max(other.xmin),
+            ymin: self.ymin.max(other.ymin),
+            xmax: self.xmax.min(other.xmax),
+            ymax: self.ymax.min(other.ymax),
+        };
+        if intersection.xmin < intersection.xmax && intersection.ymin < intersection.ymax {
+            Some(intersection)
+        } else {
+            None
+        }
+    }
+
+    // The smallest box enclosing both boxes
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            xmin: self.xmin.min(other.xmin),
+            ymin: self.ymin.min(other.ymin),
+            xmax: self.xmax.max(other.xmax),
+            ymax: self.ymax.max(other.ymax),
+        }
+    }
+
+    // Intersection over union, 0.0 when they do not overlap (or both are empty)
+    pub fn iou(&self, other: &BoundingBox) -> f32 {
+        let intersection = self.intersection(other).map_or(0, |b| b.area());
+        let union = self.area() + other.area() - intersection;
+        if union == 0 {
+            return 0.0;
+        }
+        intersection as f32 / union as f32
+    }
+
+    // true if other lies completely within this box (edges included)
+    pub fn contains(&self, other: &BoundingBox) -> bool {
+        self.xmin <= other.xmin
+            && self.ymin <= other.ymin
+            && other.xmax <= self.xmax
+            && other.ymax <= self.ymax
+    }
+    pub fn contains_point(&self, x: u32, y: u32) -> bool {
+        (self.xmin..=self.xmax).contains(&x) && (self.ymin..=self.ymax).contains(&y)
+    }
+
+    // Shortest distance between the edges of both boxes, 0.0 when they touch or overlap
+    pub fn distance(&self, other: &BoundingBox) -> f32 {
+        let dx = other.xmin.saturating_sub(self.xmax) + self.xmin.saturating_sub(other.xmax);
+        let dy = other.ymin.saturating_sub(self.ymax) + self.ymin.saturating_sub(other.ymax);
+        (dx as f32).hypot(dy as f32)
+    }
+
+    // Cuts off whatever is outside of the page; a box completely outside ends up empty
+    pub fn clamp(&self, page_width: u32, page_height: u32) -> BoundingBox {
+        BoundingBox {
+            xmin: self.xmin.min(page_width),
+            ymin: self.ymin.min(page_height),
+            xmax: self.xmax.min(page_width),
+            ymax: self.ymax.min(page_height),
+        }
+    }
+
+    // i.e. to map a box onto a resized image: scale(new_width / old_width, new_height / old_height)
+    pub fn scale(&self, scale_x: f32, scale_y: f32) -> BoundingBox {
+        BoundingBox::new(
+            to_pixel(self.xmin as f32 * scale_x),
+            to_pixel(self.ymin as f32 * scale_y),
+            to_pixel(self.xmax as f32 * scale_x),
+            to_pixel(self.ymax as f32 * scale_y),
+        )
+    }
+
+    // Grows the box by pixels on every side (stops at 0), use clamp() to keep it on the page
+    pub fn pad(&self, pixels: u32) -> BoundingBox {
+        BoundingBox {
+            xmin: self.xmin.saturating_sub(pixels),
+            ymin: self.ymin.saturating_sub(pixels),
+            xmax: self.xmax.saturating_add(pixels),
+            ymax: self.ymax.saturating_add(pixels),
+        }
+    }
+
+    pub fn from_xyxy(xmin: u32, ymin: u32, xmax: u32, ymax: u32) -> Self {
+        BoundingBox::new(xmin, ymin, xmax, ymax)
+    }
+    pub fn to_xyxy(&self) -> (u32, u32, u32, u32) {
+        (self.xmin, self.ymin, self.xmax, self.ymax)
+    }
+
+    pub fn from_xywh(center_x: f32, center_y: f32, width: f32, height: f32) -> Self {
+        BoundingBox::new(
+            to_pixel(center_x - width / 2.0),
+            to_pixel(center_y - height / 2.0),
+            to_pixel(center_x + width / 2.0),
+            to_pixel(center_y + height / 2.0),
+        )
+    }
+    pub fn to_xywh(&self) -> (f32, f32, f32, f32) {
+        let (center_x, center_y) = self.center();
+        (
+            center_x,
+            center_y,
+            self.width() as f32,
+            self.height() as f32,
+        )
+    }
+
+    pub fn from_ltwh(left: u32, top: u32, width: u32, height: u32) -> Self {
+        BoundingBox::new(
+            left,
+            top,
+            left.saturating_add(width),
+            top.saturating_add(height),
+        )
+    }
+    pub fn to_ltwh(&self) -> (u32, u32, u32, u32) {
+        (self.xmin, self.ymin, self.width(), self.height())
+    }
+
+    pub fn from_xyxy_normalized(
+        xmin: f32,
+        ymin: f32,
+        xmax: f32,
+        ymax: f32,
+        image_width: u32,
+        image_height: u32,
+    ) -> Option<Self> {
+        let (w, h) = normalized_dimensions(image_width, image_height)?;
+        Some(BoundingBox::new(
+            to_pixel(xmin * w),
+            to_pixel(ymin * h),
+            to_pixel(xmax * w),
+            to_pixel(ymax * h),
+        ))
+    }
+    pub fn to_xyxy_normalized(
+        &self,
+        image_width: u32,
+        image_height: u32,
+    ) -> Option<(f32, f32, f32, f32)> {
+        let (w, h) = normalized_dimensions(image_width, image_height)?;
+        Some((
+            self.xmin as f32 / w,
+            self.ymin as f32 / h,
+            self.xmax as f32 / w,
+            self.ymax as f32 / h,
+        ))
+    }
+
+    // YOLO
+    pub fn from_xywh_normalized(
+        center_x: f32,
+        center_y: f32,
+        width: f32,
+        height: f32,
+        image_width: u32,
+        image_height: u32,
+    ) -> Option<Self> {
+        let (w, h) = normalized_dimensions(image_width, image_height)?;
+        Some(BoundingBox::from_xywh(
+            center_x * w,
+            center_y * h,
+            width * w,
+            height * h,
+        ))
+    }
+    pub fn to_xywh_normalized(
+        &self,
+        image_width: u32,
+        image_height: u32,
+    ) -> Option<(f32, f32, f32, f32)> {
+        let (w, h) = normalized_dimensions(image_width, image_height)?;
+        let (center_x, center_y, width, height) = self.to_xywh();
+        Some((center_x / w, center_y / h, width / w, height / h))
+    }
+
+    pub fn from_ltwh_normalized(
+        left: f32,
+        top: f32,
+        width: f32,
+        height: f32,
+        image_width: u32,
+        image_height: u32,
+    ) -> Option<Self> {
+        BoundingBox::from_xyxy_normalized(
+            left,
+            top,
+            left + width,
+            top + height,
+            image_width,
+            image_height,
+        )
+    }
+    pub fn to_ltwh_normalized(
+        &self,
+        image_width: u32,
+        image_height: u32,
+    ) -> Option<(f32, f32, f32, f32)> {
+        let (w, h) = normalized_dimensions(image_width, image_height)?;
+        Some((
+            self.xmin as f32 / w,
+            self.ymin as f32 / h,
+            self.width() as f32 / w,
+            self.height() as f32 / h,
+        ))
+    }
+}
+
+impl HasBoundingBox for BoundingBox {
+    fn bounding_box(&self) -> BoundingBox {
+        *self
+    }
+}
+impl HasBoundingBox for Frame {
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::new(self.xmin, self.ymin, self.xmax, self.ymax)
+    }
+}
+impl HasBoundingBox for Text {
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::new(self.xmin, self.ymin, self.xmax, self.ymax)
+    }
+}
+impl HasBoundingBox for Face {
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::new(self.xmin, self.ymin, self.xmax, self.ymax)
+    }
+}
+impl HasBoundingBox for Body {
+    fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::new(self.xmin, self.ymin, self.xmax, self.ymax)
+    }
+}
+impl HasBoundingBox for PageElement {
+    fn bounding_box(&self) -> BoundingBox {
+        match self {
+            PageElement::Frame(frame) => frame.bounding_box(),
+            PageElement::Text(text) => text.bounding_box(),
+            PageElement::Face(face) => face.bounding_box(),
+            PageElement::Body(body) => body.bounding_box(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geometry() {
+        let a = BoundingBox::new(0, 0, 10, 10);
+        let b = BoundingBox::new(5, 5, 15, 20);
+        assert_eq!(a.area(), 100);
+        assert_eq!(a.intersection(&b), Some(BoundingBox::new(5, 5, 10, 10)));
+        assert_eq!(a.union(&b), BoundingBox::new(0, 0, 15, 20));
+        // 25 / (100 + 150 - 25)
+        assert!((a.iou(&b) - 25.0 / 225.0).abs() < 1e-6);
+        assert_eq!(a.iou(&a), 1.0);
+        // touching edges do not overlap
+        assert_eq!(a.intersection(&BoundingBox::new(10, 0, 20, 10)), None);
+        assert_eq!(a.iou(&BoundingBox::new(10, 0, 20, 10)), 0.0);
+        assert!(a.contains(&BoundingBox::new(2, 2, 10, 5)));
+        assert!(!a.contains(&b));
+        assert_eq!(a.distance(&b), 0.0);
+        assert_eq!(a.distance(&BoundingBox::new(13, 14, 20, 20)), 5.0);
+        assert_eq!(b.center(), (10.0, 12.5));
+        assert_eq!(b.clamp(12, 12), BoundingBox::new(5, 5, 12, 12));
+        assert!(b.clamp(4, 4).is_empty());
+        assert_eq!(b.scale(0.5, 2.0), BoundingBox::new(3, 10, 8, 40));
+        assert_eq!(b.pad(6), BoundingBox::new(0, 0, 21, 26));
+
+        // inverted boxes are swapped, widths of the elements do not underflow any more
+        let inverted = BoundingBox::new(10, 20, 0, 5);
+        assert_eq!(inverted, BoundingBox::new(0, 5, 10, 20));
+        let text = Text {
+            id: "00000001".to_string(),
+            xmin: 30,
+            ymin: 40,
+            xmax: 10,
+            ymax: 20,
+            value: "value".to_string(),
+        };
+        assert_eq!((text.get_width(), text.get_height()), (20, 20));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let b = BoundingBox::new(10, 20, 30, 60);
+        assert_eq!(BoundingBox::from_xyxy(10, 20, 30, 60), b);
+        assert_eq!(b.to_xywh(), (20.0, 40.0, 20.0, 40.0));
+        assert_eq!(BoundingBox::from_xywh(20.0, 40.0, 20.0, 40.0), b);
+        assert_eq!(b.to_ltwh(), (10, 20, 20, 40));
+        assert_eq!(BoundingBox::from_ltwh(10, 20, 20, 40), b);
+
+        assert_eq!(b.to_xyxy_normalized(100, 200), Some((0.1, 0.1, 0.3, 0.3)));
+        assert_eq!(b.to_xywh_normalized(100, 200), Some((0.2, 0.2, 0.2, 0.2)));
+        assert_eq!(b.to_ltwh_normalized(100, 200), Some((0.1, 0.1, 0.2, 0.2)));
+        assert_eq!(
+            BoundingBox::from_xyxy_normalized(0.1, 0.1, 0.3, 0.3, 100, 200),
+            Some(b)
+        );
+        assert_eq!(
+            BoundingBox::from_xywh_normalized(0.2, 0.2, 0.2, 0.2, 100, 200),
+            Some(b)
+        );
+        assert_eq!(
+            BoundingBox::from_ltwh_normalized(0.1, 0.1, 0.2, 0.2, 100, 200),
+            Some(b)
+        );
+
+        // a page without area (which lenient parsing lets through) has nothing to normalize against
+        assert_eq!(b.to_xywh_normalized(0, 200), None);
+        assert_eq!(b.to_xyxy_normalized(100, 0), None);
+        assert_eq!(
+            BoundingBox::from_ltwh_normalized(0.1, 0.1, 0.2, 0.2, 0, 0),
+            None
+        );
+    }
+}
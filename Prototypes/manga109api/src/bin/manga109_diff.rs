@@ -0,0 +1,125 @@
+// Diffs two annotation releases of the Manga109 dataset, book by book, i.e. to see how much of the
+// ground truth moved between v2018 and the latest annotations:
+//
+//  manga109_diff <manga109_root> <old_version> <new_version> [--verbose] [--no-cache] [title ...]
+//
+// versions are anything AnnotationVersion understands ("latest", "v2020", "v2018" or a directory
+// name), with no titles given, every book in books.txt is diffed.  --no-cache parses all the XML
+// files even when built with the "cache" feature
+use manga109api::diff::{diff_books, BookDiff, Change, ChangeCounts};
+use manga109api::{AnnotationType, AnnotationVersion, Manga109, ParseOptions};
+
+const KINDS: [AnnotationType; 4] = [
+    AnnotationType::Frame,
+    AnnotationType::Face,
+    AnnotationType::Body,
+    AnnotationType::Text,
+];
+
+fn print_counts(label: &str, counts: &ChangeCounts) {
+    println!(
+        "    {:<6} +{} -{} moved:{} text:{} character:{}",
+        label,
+        counts.added,
+        counts.removed,
+        counts.moved,
+        counts.text_changed,
+        counts.character_changed
+    );
+}
+
+fn print_diff(diff: &BookDiff, verbose: bool) {
+    println!(
+        "{}: pages +{:?} -{:?}",
+        diff.title, diff.pages_added, diff.pages_removed
+    );
+    for kind in KINDS {
+        print_counts(kind.tag(), &diff.counts(kind));
+    }
+    if !verbose {
+        return;
+    }
+    for element in diff.elements.iter() {
+        let page = match (element.old_page, element.new_page) {
+            (Some(old), Some(new)) if old != new => format!("{}->{}", old, new),
+            (Some(page), _) | (None, Some(page)) => page.to_string(),
+            (None, None) => "-".to_string(),
+        };
+        let change = match &element.change {
+            Change::Added => "added".to_string(),
+            Change::Removed => "removed".to_string(),
+            Change::Moved { old, new } => format!("moved {:?} -> {:?}", old, new),
+            Change::TextChanged { old, new } => format!("text '{}' -> '{}'", old, new),
+            Change::CharacterChanged { old, new } => format!("character {} -> {}", old, new),
+        };
+        println!(
+            "        {} {} page {}: {}",
+            element.kind.tag(),
+            element.id,
+            page,
+            change
+        );
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let verbose = args.iter().any(|arg| arg == "--verbose");
+    let no_cache = args.iter().any(|arg| arg == "--no-cache");
+    args.retain(|arg| arg != "--verbose" && arg != "--no-cache");
+    if args.len() < 3 {
+        eprintln!(
+            "Usage: manga109_diff <manga109_root> <old_version> <new_version> [--verbose] [--no-cache] [title ...]"
+        );
+        std::process::exit(2);
+    }
+    let root_dir = &args[0];
+    let old_version: AnnotationVersion = args[1].parse().unwrap_or_default();
+    let new_version: AnnotationVersion = args[2].parse().unwrap_or_default();
+
+    let open = |version: &AnnotationVersion| {
+        let manga109 = Manga109::new_with_version(root_dir, version, &ParseOptions::default())
+            .unwrap_or_else(|e| {
+                eprintln!("Unable to open '{}' ({}): {}", root_dir, version, e);
+                std::process::exit(1);
+            });
+        if no_cache {
+            manga109.without_cache()
+        } else {
+            manga109
+        }
+    };
+    let old = open(&old_version);
+    let new = open(&new_version);
+
+    let titles: Vec<String> = if args.len() > 3 {
+        args[3..].to_vec()
+    } else {
+        new.titles().to_vec()
+    };
+    let mut total = [ChangeCounts::default(); KINDS.len()];
+    for title in titles.iter() {
+        let (old_book, new_book) = match (old.book(title), new.book(title)) {
+            (Ok(old_book), Ok(new_book)) => (old_book, new_book),
+            (Err(e), _) | (_, Err(e)) => {
+                println!("WARNING: Skipping book '{}': {}", title, e);
+                continue;
+            }
+        };
+        let diff = diff_books(old_book, new_book);
+        print_diff(&diff, verbose);
+        for (i, kind) in KINDS.iter().enumerate() {
+            let counts = diff.counts(*kind);
+            total[i].added += counts.added;
+            total[i].removed += counts.removed;
+            total[i].moved += counts.moved;
+            total[i].text_changed += counts.text_changed;
+            total[i].character_changed += counts.character_changed;
+        }
+    }
+
+    println!("Total ({} -> {}):", old_version, new_version);
+    for (kind, counts) in KINDS.iter().zip(total.iter()) {
+        print_counts(kind.tag(), counts);
+    }
+}
@@ -0,0 +1,52 @@
+// Generates a synthetic Manga109 dataset root (see manga109api::fixture) for testing the tools
+// without the licensed dataset:
+//
+//  manga109_fixture <output_root> [--books N] [--pages N] [--frames N] [--texts N] [--faces N]
+//      [--characters N] [--width W] [--height H] [--seed N]
+//
+// any option left out keeps its FixtureOptions::default() value
+use manga109api::fixture::{generate, FixtureOptions};
+
+const USAGE: &str = "Usage: manga109_fixture <output_root> [--books N] [--pages N] [--frames N] [--texts N] [--faces N] [--characters N] [--width W] [--height H] [--seed N]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn number<T: std::str::FromStr>(value: Option<String>) -> T {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| usage())
+}
+
+fn main() {
+    let mut root_dir = None;
+    let mut options = FixtureOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--books" => options.books = number(args.next()),
+            "--pages" => options.pages_per_book = number(args.next()),
+            "--frames" => options.frames_per_page = number(args.next()),
+            "--texts" => options.texts_per_frame = number(args.next()),
+            "--faces" => options.faces_per_frame = number(args.next()),
+            "--characters" => options.characters_per_book = number(args.next()),
+            "--width" => options.page_width = number(args.next()),
+            "--height" => options.page_height = number(args.next()),
+            "--seed" => options.seed = number(args.next()),
+            _ if root_dir.is_none() && !arg.starts_with("--") => root_dir = Some(arg),
+            _ => usage(),
+        }
+    }
+    let Some(root_dir) = root_dir else { usage() };
+
+    generate(&root_dir, &options).unwrap_or_else(|e| {
+        eprintln!("Unable to generate '{}': {}", root_dir, e);
+        std::process::exit(1);
+    });
+    eprintln!(
+        "Generated {} book(s) of {} page(s) in '{}'",
+        options.books, options.pages_per_book, root_dir
+    );
+}
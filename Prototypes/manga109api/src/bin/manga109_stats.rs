@@ -0,0 +1,85 @@
+// Dataset statistics of the Manga109 corpus (pages per book, text boxes per page, text box sizes
+// and aspect ratios, characters per text, kanji/kana, faces per character), per book and in total:
+//
+//  manga109_stats <manga109_root> [--version <version>] [--json <path>] [--markdown <path>] [--no-cache]
+//
+// the Markdown report goes to stdout unless --markdown is given; books which fail to parse are
+// reported and left out.  --no-cache parses all the XML files even when built with the "cache"
+// feature
+use manga109api::stats::report;
+use manga109api::{AnnotationVersion, Manga109, ParseOptions};
+
+const USAGE: &str = "Usage: manga109_stats <manga109_root> [--version <version>] [--json <path>] [--markdown <path>] [--no-cache]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut root_dir = None;
+    let mut version = AnnotationVersion::default();
+    let mut json_path = None;
+    let mut markdown_path = None;
+    let mut no_cache = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--version" => {
+                version = args
+                    .next()
+                    .unwrap_or_else(|| usage())
+                    .parse()
+                    .unwrap_or_default()
+            }
+            "--json" => json_path = Some(args.next().unwrap_or_else(|| usage())),
+            "--markdown" => markdown_path = Some(args.next().unwrap_or_else(|| usage())),
+            "--no-cache" => no_cache = true,
+            _ if root_dir.is_none() && !arg.starts_with("--") => root_dir = Some(arg),
+            _ => usage(),
+        }
+    }
+    let Some(root_dir) = root_dir else { usage() };
+
+    let manga109 = Manga109::new_with_version(&root_dir, &version, &ParseOptions::default())
+        .unwrap_or_else(|e| {
+            eprintln!("Unable to open '{}' ({}): {}", root_dir, version, e);
+            std::process::exit(1);
+        });
+    let manga109 = if no_cache {
+        manga109.without_cache()
+    } else {
+        manga109
+    };
+    let books =
+        manga109
+            .load_all_results()
+            .into_iter()
+            .filter_map(|(title, result)| match result {
+                Ok(book) => Some(book),
+                Err(e) => {
+                    eprintln!("WARNING: Skipping book '{}': {}", title, e);
+                    None
+                }
+            });
+    let report = report(books);
+
+    if let Some(json_path) = json_path {
+        let json = serde_json::to_string_pretty(&report).unwrap_or_else(|e| {
+            eprintln!("Unable to serialize the report: {}", e);
+            std::process::exit(1);
+        });
+        std::fs::write(&json_path, json).unwrap_or_else(|e| {
+            eprintln!("Unable to write '{}': {}", json_path, e);
+            std::process::exit(1);
+        });
+    }
+    let markdown = report.to_markdown();
+    match markdown_path {
+        Some(markdown_path) => std::fs::write(&markdown_path, markdown).unwrap_or_else(|e| {
+            eprintln!("Unable to write '{}': {}", markdown_path, e);
+            std::process::exit(1);
+        }),
+        None => print!("{}", markdown),
+    }
+}
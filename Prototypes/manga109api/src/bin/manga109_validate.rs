@@ -0,0 +1,56 @@
+// Lints a Manga109 dataset root (see manga109api::validate), printing one JSON object per issue
+// (JSON lines) to stdout and a summary to stderr:
+//
+//  manga109_validate <manga109_root> [--version <version>]
+//
+// exits with 1 when there is any issue (and 2 when the dataset cannot be validated at all), so
+// that dataset updates can be gated on it
+use std::io::Write;
+
+use manga109api::validate::validate;
+use manga109api::AnnotationVersion;
+
+const USAGE: &str = "Usage: manga109_validate <manga109_root> [--version <version>]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut root_dir = None;
+    let mut version = AnnotationVersion::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--version" => {
+                version = args
+                    .next()
+                    .unwrap_or_else(|| usage())
+                    .parse()
+                    .unwrap_or_default()
+            }
+            _ if root_dir.is_none() && !arg.starts_with("--") => root_dir = Some(arg),
+            _ => usage(),
+        }
+    }
+    let Some(root_dir) = root_dir else { usage() };
+
+    let issues = validate(&root_dir, &version).unwrap_or_else(|e| {
+        eprintln!("Unable to validate '{}' ({}): {}", root_dir, version, e);
+        std::process::exit(2);
+    });
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    for issue in issues.iter() {
+        serde_json::to_writer(&mut stdout, issue).expect("Unable to write to stdout");
+        writeln!(stdout).expect("Unable to write to stdout");
+    }
+
+    let books: std::collections::BTreeSet<&str> =
+        issues.iter().map(|issue| issue.title.as_str()).collect();
+    eprintln!("{} issue(s) in {} book(s)", issues.len(), books.len());
+    if !issues.is_empty() {
+        std::process::exit(1);
+    }
+}
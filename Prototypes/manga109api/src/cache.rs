@@ -0,0 +1,259 @@
+// Binary (bincode) cache of the parsed books, so that the 109 annotation XML files are not parsed
+// again on every run:
+//  <cache_dir>/<annotation version dir>/<title>.bin
+// where cache_dir is <user cache dir>/manga109api/<hash of the absolute root_dir> (i.e.
+// ~/.cache/manga109api/0123456789abcdef on Linux) unless set with Manga109::with_cache_dir(); the
+// dataset directory itself is never written to, since it may be shared, read-only or versioned.
+// Each file starts with a CacheKey; it is only used when the key matches the XML as it is now
+// (size, mtime and a hash of the content) and the ParseOptions, otherwise the XML is parsed and the
+// file rewritten.  After the key come the book and its diagnostics, then the document order of
+// each page (which serde leaves out of a Page).  The cache is best effort: a cache file which
+// cannot be read or written (i.e. a read-only dataset directory) simply means parsing the XML.
+// To bypass it, use Manga109::without_cache() or set MANGA109API_NO_CACHE (to anything).
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    normalize_paths, parse_raw_xml_annotations_with_options, AnnotationType, AnnotationVersion,
+    Book, Error, Manga109, ParseDiagnostic, ParseOptions, Result,
+};
+
+// Page::order of every page of the book
+type PageOrders = Vec<Vec<(AnnotationType, usize)>>;
+
+// under the user cache directory (see dirs::cache_dir())
+pub const CACHE_DIR_NAME: &str = "manga109api";
+pub const NO_CACHE_ENV: &str = "MANGA109API_NO_CACHE";
+// bump whenever anything which goes into the cache files changes shape
+const CACHE_FORMAT: u32 = 2;
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CacheKey {
+    format: u32,
+    size: u64,
+    modified: (u64, u32), // seconds and nanoseconds since the epoch
+    hash: u64,
+    strict: bool,
+}
+impl CacheKey {
+    fn new(xml: &[u8], metadata: &std::fs::Metadata, options: &ParseOptions) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since| (since.as_secs(), since.subsec_nanos()))
+            .unwrap_or_default();
+        CacheKey {
+            format: CACHE_FORMAT,
+            size: metadata.len(),
+            modified,
+            hash: fnv1a(xml),
+            strict: options.strict,
+        }
+    }
+}
+
+// FNV-1a, 64 bits; unlike std's DefaultHasher, it is the same on every build
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+// None (no caching) when NO_CACHE_ENV is set or there is no user cache directory; unit tests never
+// write into the user's cache either (test_cache sets its own directory)
+pub(crate) fn default_cache_dir(root_dir: &str) -> Option<PathBuf> {
+    if cfg!(test) || std::env::var_os(NO_CACHE_ENV).is_some() {
+        return None;
+    }
+    // one directory per dataset root, so that two copies of the dataset do not share files
+    let root_path = std::fs::canonicalize(root_dir).unwrap_or_else(|_| PathBuf::from(root_dir));
+    let root_hash = fnv1a(root_path.to_string_lossy().as_bytes());
+    Some(
+        dirs::cache_dir()?
+            .join(CACHE_DIR_NAME)
+            .join(format!("{:016x}", root_hash)),
+    )
+}
+
+fn cache_path(cache_dir: &Path, annotation_version: &AnnotationVersion, title: &str) -> PathBuf {
+    cache_dir
+        .join(annotation_version.dir_name())
+        .join(format!("{}.bin", title))
+}
+
+fn read_cache(path: &Path, key: &CacheKey) -> Option<(Book, Vec<ParseDiagnostic>)> {
+    let mut reader = BufReader::new(std::fs::File::open(path).ok()?);
+    let cached_key: CacheKey = bincode::deserialize_from(&mut reader).ok()?;
+    if cached_key != *key {
+        return None;
+    }
+    let (mut book, diagnostics): (Book, Vec<ParseDiagnostic>) =
+        bincode::deserialize_from(&mut reader).ok()?;
+    let orders: PageOrders = bincode::deserialize_from(&mut reader).ok()?;
+    if orders.len() != book.pages.len() {
+        return None;
+    }
+    for (page, order) in book.pages.iter_mut().zip(orders) {
+        page.order = order;
+    }
+    Some((book, diagnostics))
+}
+
+fn write_cache(
+    path: &Path,
+    key: &CacheKey,
+    parsed: &(Book, Vec<ParseDiagnostic>),
+) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // written aside and then renamed, so that a reader never sees half a file
+    let temp_path = path.with_extension(format!("bin.{}", std::process::id()));
+    let write = || -> std::io::Result<()> {
+        let mut writer = BufWriter::new(std::fs::File::create(&temp_path)?);
+        bincode::serialize_into(&mut writer, key).map_err(std::io::Error::other)?;
+        bincode::serialize_into(&mut writer, parsed).map_err(std::io::Error::other)?;
+        let orders: PageOrders = parsed
+            .0
+            .pages
+            .iter()
+            .map(|page| page.order.clone())
+            .collect();
+        bincode::serialize_into(&mut writer, &orders).map_err(std::io::Error::other)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+    };
+    let written = write().and_then(|_| std::fs::rename(&temp_path, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    written
+}
+
+// Same as get_book_with_options(), but through the cache
+pub(crate) fn get_book_cached(
+    cache_dir: &Path,
+    root_dir: &str,
+    title: &str,
+    annotation_version: &AnnotationVersion,
+    options: &ParseOptions,
+) -> Result<(Book, Vec<ParseDiagnostic>)> {
+    let root_path = Path::new(root_dir);
+    let xml_path = root_path
+        .join(annotation_version.dir_name())
+        .join(format!("{}.xml", title));
+    let image_dir = root_path.join("images").join(title);
+    if !xml_path.exists() {
+        return Err(Error::MissingAnnotationFile(xml_path));
+    }
+    if !image_dir.exists() {
+        return Err(Error::MissingBookImageDir(image_dir));
+    }
+    let io_error = |e| Error::Io {
+        path: xml_path.clone(),
+        source: e,
+    };
+    let xml = std::fs::read(&xml_path).map_err(io_error)?;
+    let key = CacheKey::new(
+        &xml,
+        &std::fs::metadata(&xml_path).map_err(io_error)?,
+        options,
+    );
+    let xml_pathsname = normalize_paths(&xml_path.to_string_lossy());
+    let image_pathsname = normalize_paths(&image_dir.to_string_lossy());
+
+    let path = cache_path(cache_dir, annotation_version, title);
+    if let Some((mut book, diagnostics)) = read_cache(&path, &key) {
+        // the paths are machine specific, so they are not in the cache
+        book.annotation_filepaths = xml_pathsname;
+        book.image_root_dir = image_pathsname;
+        book.index_pages();
+        return Ok((book, diagnostics));
+    }
+
+    let xml = String::from_utf8(xml)
+        .map_err(|e| io_error(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+    let parsed =
+        parse_raw_xml_annotations_with_options(&xml, &xml_pathsname, &image_pathsname, options)?;
+    let _ = write_cache(&path, &key, &parsed);
+    Ok(parsed)
+}
+
+impl Manga109 {
+    // Cache the parsed books in this directory rather than the default one in the user cache
+    // directory
+    pub fn with_cache_dir<P: Into<PathBuf>>(self, cache_dir: P) -> Self {
+        Manga109 {
+            cache_dir: Some(cache_dir.into()),
+            ..self
+        }
+    }
+
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let root = std::env::temp_dir().join(format!("manga109api_cache_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("annotations")).unwrap();
+        std::fs::create_dir_all(root.join("images/Cached")).unwrap();
+        std::fs::write(root.join("books.txt"), "Cached\n").unwrap();
+        let xml_path = root.join("annotations/Cached.xml");
+        let xml = r#"<book title="Cached"><pages>
+                <page index="0" width="100" height="200">
+                    <text id="00000003" xmin="10" ymin="20" xmax="30" ymax="40">あ</text>
+                    <face id="00000001" xmin="10" ymin="20" xmax="30" ymax="40" character="0000000a"/>
+                    <text id="00000002" xmin="10" ymin="20" xmax="30" ymax="40">はい</text>
+                </page>
+            </pages></book>"#;
+        std::fs::write(&xml_path, xml).unwrap();
+        let root_dir = root.to_str().unwrap();
+        let cache_dir = root.join("cache");
+        let open = || Manga109::new(root_dir).unwrap().with_cache_dir(&cache_dir);
+
+        let uncached = Manga109::new(root_dir).unwrap().without_cache();
+        assert!(uncached.cache_dir().is_none());
+        let expected = uncached.book("Cached").unwrap();
+        assert!(!cache_dir.exists());
+
+        let first = open();
+        assert_eq!(first.book("Cached").unwrap(), expected);
+        let path = cache_path(&cache_dir, &AnnotationVersion::Latest, "Cached");
+        let metadata = std::fs::metadata(&xml_path).unwrap();
+        let key = CacheKey::new(xml.as_bytes(), &metadata, &ParseOptions::default());
+        assert!(read_cache(&path, &key).is_some());
+        // a lenient parse is not the same thing
+        let lenient = CacheKey::new(xml.as_bytes(), &metadata, &ParseOptions::lenient());
+        assert!(read_cache(&path, &lenient).is_none());
+
+        // from the cache, the document order of the elements and the paths included
+        let second = open();
+        let book = second.book("Cached").unwrap();
+        assert_eq!(book, expected);
+        assert_eq!(book.pages[0].elements(), expected.pages[0].elements());
+        assert_eq!(
+            book.get_annotation_file_paths(),
+            expected.get_annotation_file_paths()
+        );
+
+        // a changed XML is parsed again
+        std::fs::write(&xml_path, xml.replace("はい", "いいえ")).unwrap();
+        let third = open();
+        assert_eq!(
+            third.book("Cached").unwrap().pages[0].texts[1].value,
+            "いいえ"
+        );
+        assert!(read_cache(&path, &key).is_none());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
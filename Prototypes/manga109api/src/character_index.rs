@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use crate::{
+    AnnotationType, Book, BoundingBox, Character, Frame, HasBoundingBox, Page,
+    DEFAULT_MIN_OVERLAP_RATIO,
+};
+
+// One face or body of a character
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Appearance<'a> {
+    pub page: &'a Page,
+    pub frame: Option<&'a Frame>, // None when it is not inside any frame (see Page::frame_of())
+    pub kind: AnnotationType,     // Face or Body
+    pub id: &'a str,              // of the face/body
+    pub bounding_box: BoundingBox,
+}
+
+// Per character summary of a book, see CharacterIndex::cast()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CastMember<'a> {
+    pub character: &'a Character,
+    pub faces: usize,
+    pub bodies: usize,
+    pub pages: usize,              // number of (distinct) pages the character is on
+    pub first_page: Option<usize>, // Page::index, None if the character never appears
+    pub last_page: Option<usize>,
+}
+
+// Resolves the bare Face::character/Body::character ids of a book, and lists where each character
+// appears:
+//  let index = book.character_index();
+//  for appearance in index.appearances("00000003") { ... }
+// Ids used by faces/bodies but not declared in the book's <characters> are still indexed (so their
+// appearances can be listed) but do not resolve, see dangling_ids().
+#[derive(Debug, Clone)]
+pub struct CharacterIndex<'a> {
+    book: &'a Book,
+    characters: HashMap<&'a str, &'a Character>,
+    appearances: HashMap<&'a str, Vec<Appearance<'a>>>, // in page order, faces before bodies
+}
+
+impl<'a> CharacterIndex<'a> {
+    pub fn new(book: &'a Book) -> Self {
+        let characters = book
+            .characters
+            .iter()
+            .map(|character| (character.id.as_str(), character))
+            .collect();
+        let mut appearances: HashMap<&str, Vec<Appearance>> = HashMap::new();
+        for page in book.pages.iter() {
+            let faces = page.faces.iter().map(|face| {
+                (
+                    AnnotationType::Face,
+                    face.id.as_str(),
+                    face.character.as_str(),
+                    face.bounding_box(),
+                )
+            });
+            let bodies = page.bodies.iter().map(|body| {
+                (
+                    AnnotationType::Body,
+                    body.id.as_str(),
+                    body.character.as_str(),
+                    body.bounding_box(),
+                )
+            });
+            for (kind, id, character, bounding_box) in faces.chain(bodies) {
+                let frame = page
+                    .frame_of(&bounding_box, DEFAULT_MIN_OVERLAP_RATIO)
+                    .map(|position| &page.frames[position]);
+                appearances.entry(character).or_default().push(Appearance {
+                    page,
+                    frame,
+                    kind,
+                    id,
+                    bounding_box,
+                });
+            }
+        }
+        CharacterIndex {
+            book,
+            characters,
+            appearances,
+        }
+    }
+
+    pub fn character(&self, id: &str) -> Option<&'a Character> {
+        self.characters.get(id).copied()
+    }
+
+    // Every face and body of the character, in page order
+    pub fn appearances(&self, id: &str) -> &[Appearance<'a>] {
+        self.appearances
+            .get(id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    // Number of faces plus bodies (so a character drawn with both face and body counts twice)
+    pub fn count(&self, id: &str) -> usize {
+        self.appearances(id).len()
+    }
+
+    // Page::index of every page the character is on, in page order and without repeats
+    pub fn timeline(&self, id: &str) -> Vec<usize> {
+        let mut pages: Vec<usize> = Vec::new();
+        for appearance in self.appearances(id) {
+            if pages.last() != Some(&appearance.page.index) {
+                pages.push(appearance.page.index);
+            }
+        }
+        pages
+    }
+
+    // Character ids used by faces/bodies which are not declared in the book's <characters>, sorted
+    pub fn dangling_ids(&self) -> Vec<&'a str> {
+        let mut ids: Vec<&str> = self
+            .appearances
+            .keys()
+            .copied()
+            .filter(|id| !self.characters.contains_key(id))
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    // One entry per declared character (including those who never appear), in <characters> order
+    pub fn cast(&self) -> Vec<CastMember<'a>> {
+        self.book
+            .characters
+            .iter()
+            .map(|character| {
+                let appearances = self.appearances(&character.id);
+                let timeline = self.timeline(&character.id);
+                CastMember {
+                    character,
+                    faces: appearances
+                        .iter()
+                        .filter(|a| a.kind == AnnotationType::Face)
+                        .count(),
+                    bodies: appearances
+                        .iter()
+                        .filter(|a| a.kind == AnnotationType::Body)
+                        .count(),
+                    pages: timeline.len(),
+                    first_page: timeline.first().copied(),
+                    last_page: timeline.last().copied(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Book {
+    pub fn character_index(&self) -> CharacterIndex<'_> {
+        CharacterIndex::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_raw_xml_annotations_and_make_book;
+
+    #[test]
+    fn test_character_index() {
+        let book = parse_raw_xml_annotations_and_make_book(
+            r#"<book title="title">
+                <characters>
+                    <character id="0000000a" name="Alice"/>
+                    <character id="0000000b" name="Bob"/>
+                    <character id="0000000c" name="Carol"/>
+                </characters>
+                <pages>
+                    <page index="0" width="400" height="200">
+                        <frame id="00000001" xmin="0" ymin="0" xmax="200" ymax="200"/>
+                        <face id="00000002" xmin="10" ymin="10" xmax="50" ymax="50" character="0000000a"/>
+                        <body id="00000003" xmin="10" ymin="10" xmax="60" ymax="150" character="0000000a"/>
+                        <face id="00000004" xmin="300" ymin="10" xmax="350" ymax="50" character="0000000b"/>
+                    </page>
+                    <page index="1" width="400" height="200"/>
+                    <page index="2" width="400" height="200">
+                        <face id="00000005" xmin="10" ymin="10" xmax="50" ymax="50" character="0000000a"/>
+                        <body id="00000006" xmin="10" ymin="10" xmax="60" ymax="150" character="0000000f"/>
+                    </page>
+                </pages>
+            </book>"#,
+            "",
+            "",
+        )
+        .unwrap();
+        let index = book.character_index();
+
+        assert_eq!(index.character("0000000b").unwrap().name, "Bob");
+        assert!(index.character("0000000f").is_none());
+        assert_eq!(index.count("0000000a"), 3);
+        assert_eq!(index.timeline("0000000a"), vec![0, 2]);
+        let ids: Vec<(&str, Option<&str>)> = index
+            .appearances("0000000a")
+            .iter()
+            .map(|a| (a.id, a.frame.map(|frame| frame.id.as_str())))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("00000002", Some("00000001")),
+                ("00000003", Some("00000001")),
+                ("00000005", None),
+            ]
+        );
+        // Bob is outside the frame as well
+        assert!(index.appearances("0000000b")[0].frame.is_none());
+
+        assert_eq!(index.dangling_ids(), vec!["0000000f"]);
+        assert_eq!(index.count("0000000f"), 1);
+
+        let cast: Vec<_> = index
+            .cast()
+            .iter()
+            .map(|m| {
+                (
+                    m.character.name.as_str(),
+                    m.faces,
+                    m.bodies,
+                    m.pages,
+                    m.first_page,
+                    m.last_page,
+                )
+            })
+            .collect();
+        assert_eq!(
+            cast,
+            vec![
+                ("Alice", 2, 1, 2, Some(0), Some(2)),
+                ("Bob", 1, 0, 1, Some(0), Some(0)),
+                ("Carol", 0, 0, 0, None, None),
+            ]
+        );
+    }
+}
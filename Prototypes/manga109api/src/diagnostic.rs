@@ -0,0 +1,83 @@
+use std::fmt;
+
+// Controls how forgiving the annotation parser is about defective elements.
+// * strict (default): a missing/invalid attribute or an empty <text/> is returned as an Error, and
+//   bounding boxes are kept as written (inverted or outside of the page included), which is how
+//   the parser has always behaved; see validate() to find such boxes
+// * lenient: defective elements are either repaired or skipped, and each one of them is reported
+//   back as a ParseDiagnostic so that nothing is dropped silently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub strict: bool,
+}
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions { strict: true }
+    }
+}
+impl ParseOptions {
+    pub fn lenient() -> Self {
+        ParseOptions { strict: false }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Defect {
+    MissingAttribute(String),
+    InvalidAttribute { attribute: String, value: String },
+    EmptyText,   // <text/> is marked as nillable in annotations.xsd
+    InvertedBox, // xmin > xmax and/or ymin > ymax
+    OutsidePage, // xmax and/or ymax beyond the page width/height
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseAction {
+    Repaired, // element was kept, but with its values fixed up (i.e. swapped or clamped coordinates)
+    Skipped,  // element was dropped from the Book
+}
+
+// One defect found (and dealt with) while parsing in lenient mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseDiagnostic {
+    pub title: String,       // book title
+    pub page: Option<usize>, // page index, None if the defect is outside of <page> (i.e. <character>)
+    pub element: String,     // tag name, i.e. "text"
+    pub id: Option<String>,  // None when the element has no (or an unreadable) id
+    pub defect: Defect,
+    pub action: ParseAction,
+}
+
+impl fmt::Display for Defect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Defect::MissingAttribute(attribute) => write!(f, "missing attribute '{}'", attribute),
+            Defect::InvalidAttribute { attribute, value } => {
+                write!(f, "attribute '{}' has invalid value '{}'", attribute, value)
+            }
+            Defect::EmptyText => write!(f, "empty text"),
+            Defect::InvertedBox => write!(f, "inverted bounding box"),
+            Defect::OutsidePage => write!(f, "bounding box outside of page"),
+        }
+    }
+}
+
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.title)?;
+        if let Some(page) = self.page {
+            write!(f, " page {}", page)?;
+        }
+        write!(f, " <{}", self.element)?;
+        if let Some(id) = &self.id {
+            write!(f, " id=\"{}\"", id)?;
+        }
+        let action = match self.action {
+            ParseAction::Repaired => "repaired",
+            ParseAction::Skipped => "skipped",
+        };
+        write!(f, ">: {} ({})", self.defect, action)
+    }
+}
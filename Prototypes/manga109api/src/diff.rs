@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+
+use crate::{AnnotationType, Book};
+
+// (xmin, ymin, xmax, ymax) as found in the XML
+pub type Rect = (u32, u32, u32, u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Moved { old: Rect, new: Rect }, // bounding box changed and/or the element is on another page
+    TextChanged { old: String, new: String },
+    CharacterChanged { old: String, new: String }, // face/body only
+}
+
+// A single difference of one element (matched by tag and id) between two releases of the same book.
+// An element which both moved and got its text changed shows up twice, once for each Change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementDiff {
+    pub kind: AnnotationType,
+    pub id: String,
+    pub old_page: Option<usize>, // None when Added
+    pub new_page: Option<usize>, // None when Removed
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BookDiff {
+    pub title: String,
+    pub pages_added: Vec<usize>,
+    pub pages_removed: Vec<usize>,
+    pub elements: Vec<ElementDiff>,
+}
+
+// per AnnotationType counts of BookDiff::elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChangeCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub moved: usize,
+    pub text_changed: usize,
+    pub character_changed: usize,
+}
+
+impl BookDiff {
+    pub fn is_empty(&self) -> bool {
+        self.pages_added.is_empty() && self.pages_removed.is_empty() && self.elements.is_empty()
+    }
+
+    pub fn counts(&self, kind: AnnotationType) -> ChangeCounts {
+        let mut counts = ChangeCounts::default();
+        for element in self.elements.iter().filter(|e| e.kind == kind) {
+            match element.change {
+                Change::Added => counts.added += 1,
+                Change::Removed => counts.removed += 1,
+                Change::Moved { .. } => counts.moved += 1,
+                Change::TextChanged { .. } => counts.text_changed += 1,
+                Change::CharacterChanged { .. } => counts.character_changed += 1,
+            }
+        }
+        counts
+    }
+}
+
+// Everything of an element which can change between releases, keyed by (tag, id)
+struct Snapshot<'a> {
+    page: usize,
+    rect: Rect,
+    text: Option<&'a str>,      // text only
+    character: Option<&'a str>, // face/body only
+}
+
+fn snapshots(book: &Book) -> BTreeMap<(AnnotationType, &str), Snapshot<'_>> {
+    let mut map = BTreeMap::new();
+    for page in book.pages.iter() {
+        for frame in page.frames.iter() {
+            map.insert(
+                (AnnotationType::Frame, frame.id.as_str()),
+                Snapshot {
+                    page: page.index,
+                    rect: (frame.xmin, frame.ymin, frame.xmax, frame.ymax),
+                    text: None,
+                    character: None,
+                },
+            );
+        }
+        for text in page.texts.iter() {
+            map.insert(
+                (AnnotationType::Text, text.id.as_str()),
+                Snapshot {
+                    page: page.index,
+                    rect: (text.xmin, text.ymin, text.xmax, text.ymax),
+                    text: Some(text.value.as_str()),
+                    character: None,
+                },
+            );
+        }
+        for face in page.faces.iter() {
+            map.insert(
+                (AnnotationType::Face, face.id.as_str()),
+                Snapshot {
+                    page: page.index,
+                    rect: (face.xmin, face.ymin, face.xmax, face.ymax),
+                    text: None,
+                    character: Some(face.character.as_str()),
+                },
+            );
+        }
+        for body in page.bodies.iter() {
+            map.insert(
+                (AnnotationType::Body, body.id.as_str()),
+                Snapshot {
+                    page: page.index,
+                    rect: (body.xmin, body.ymin, body.xmax, body.ymax),
+                    text: None,
+                    character: Some(body.character.as_str()),
+                },
+            );
+        }
+    }
+    map
+}
+
+// Diffs two parsed releases of the same title (i.e. the v2018 and the latest annotations of
+// "ARMS"); elements are matched by tag and id, pages by their index.  The element diffs are
+// ordered by tag (frame, face, body, text) and then by id.
+pub fn diff_books(old: &Book, new: &Book) -> BookDiff {
+    let old_pages: Vec<usize> = old.pages.iter().map(|p| p.index).collect();
+    let new_pages: Vec<usize> = new.pages.iter().map(|p| p.index).collect();
+    let mut diff = BookDiff {
+        title: new.title.clone(),
+        pages_added: new_pages
+            .iter()
+            .filter(|index| !old_pages.contains(index))
+            .copied()
+            .collect(),
+        pages_removed: old_pages
+            .iter()
+            .filter(|index| !new_pages.contains(index))
+            .copied()
+            .collect(),
+        elements: Vec::new(),
+    };
+
+    let old_elements = snapshots(old);
+    let new_elements = snapshots(new);
+    for ((kind, id), before) in old_elements.iter() {
+        let Some(after) = new_elements.get(&(*kind, *id)) else {
+            diff.elements.push(ElementDiff {
+                kind: *kind,
+                id: id.to_string(),
+                old_page: Some(before.page),
+                new_page: None,
+                change: Change::Removed,
+            });
+            continue;
+        };
+        let mut push = |change: Change| {
+            diff.elements.push(ElementDiff {
+                kind: *kind,
+                id: id.to_string(),
+                old_page: Some(before.page),
+                new_page: Some(after.page),
+                change,
+            })
+        };
+        if before.page != after.page || before.rect != after.rect {
+            push(Change::Moved {
+                old: before.rect,
+                new: after.rect,
+            });
+        }
+        if before.text != after.text {
+            push(Change::TextChanged {
+                old: before.text.unwrap_or_default().to_string(),
+                new: after.text.unwrap_or_default().to_string(),
+            });
+        }
+        if before.character != after.character {
+            push(Change::CharacterChanged {
+                old: before.character.unwrap_or_default().to_string(),
+                new: after.character.unwrap_or_default().to_string(),
+            });
+        }
+    }
+    for ((kind, id), after) in new_elements.iter() {
+        if !old_elements.contains_key(&(*kind, *id)) {
+            diff.elements.push(ElementDiff {
+                kind: *kind,
+                id: id.to_string(),
+                old_page: None,
+                new_page: Some(after.page),
+                change: Change::Added,
+            });
+        }
+    }
+    diff.elements
+        .sort_by(|a, b| (a.kind, a.id.as_str()).cmp(&(b.kind, b.id.as_str())));
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_raw_xml_annotations_and_make_book;
+
+    #[test]
+    fn test_diff_books() {
+        let old = parse_raw_xml_annotations_and_make_book(
+            r#"<book title="title"><pages>
+                <page index="0" width="100" height="200">
+                    <frame id="00000001" xmin="0" ymin="0" xmax="50" ymax="50"/>
+                    <text id="00000002" xmin="10" ymin="20" xmax="30" ymax="40">before</text>
+                    <face id="00000003" xmin="10" ymin="20" xmax="30" ymax="40" character="0000000a"/>
+                    <body id="00000004" xmin="10" ymin="20" xmax="30" ymax="40" character="0000000a"/>
+                </page>
+                <page index="1" width="100" height="200"/>
+            </pages></book>"#,
+            "",
+            "",
+        )
+        .unwrap();
+        let new = parse_raw_xml_annotations_and_make_book(
+            r#"<book title="title"><pages>
+                <page index="0" width="100" height="200">
+                    <frame id="00000001" xmin="0" ymin="0" xmax="50" ymax="50"/>
+                    <text id="00000002" xmin="12" ymin="20" xmax="30" ymax="40">after</text>
+                    <face id="00000003" xmin="10" ymin="20" xmax="30" ymax="40" character="0000000b"/>
+                    <text id="00000005" xmin="10" ymin="20" xmax="30" ymax="40">new</text>
+                </page>
+                <page index="2" width="100" height="200"/>
+            </pages></book>"#,
+            "",
+            "",
+        )
+        .unwrap();
+
+        assert!(diff_books(&old, &old).is_empty());
+
+        let diff = diff_books(&old, &new);
+        assert_eq!(diff.pages_added, vec![2]);
+        assert_eq!(diff.pages_removed, vec![1]);
+        let changes: Vec<_> = diff
+            .elements
+            .iter()
+            .map(|e| (e.kind, e.id.as_str(), &e.change))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    AnnotationType::Face,
+                    "00000003",
+                    &Change::CharacterChanged {
+                        old: "0000000a".to_string(),
+                        new: "0000000b".to_string()
+                    }
+                ),
+                (AnnotationType::Body, "00000004", &Change::Removed),
+                (
+                    AnnotationType::Text,
+                    "00000002",
+                    &Change::Moved {
+                        old: (10, 20, 30, 40),
+                        new: (12, 20, 30, 40)
+                    }
+                ),
+                (
+                    AnnotationType::Text,
+                    "00000002",
+                    &Change::TextChanged {
+                        old: "before".to_string(),
+                        new: "after".to_string()
+                    }
+                ),
+                (AnnotationType::Text, "00000005", &Change::Added),
+            ]
+        );
+        assert_eq!(
+            diff.counts(AnnotationType::Text),
+            ChangeCounts {
+                added: 1,
+                moved: 1,
+                text_changed: 1,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+use std::path::PathBuf;
+
+// Everything that can go wrong while locating, reading or parsing the Manga109 dataset.
+// The intention is that a single malformed book (or page) should never take down a whole
+// pipeline, so every public entry point of this crate returns one of these rather than panic!()
+#[derive(Debug)]
+pub enum Error {
+    // dataset layout
+    MissingRootDir(PathBuf),
+    MissingAnnotationsDir(PathBuf),
+    MissingImagesDir(PathBuf),
+    EmptyBookList(PathBuf), // books.txt exists but has no titles in it
+
+    // book lookup; `title` is the (case-sensitive) title as it appears in books.txt
+    MissingBook(String),
+    MissingAnnotationFile(PathBuf),
+    MissingBookImageDir(PathBuf),
+
+    // annotation XML
+    Xml {
+        path: String, // empty when parsing from a raw string
+        line: u32,
+        column: u32,
+        message: String,
+    },
+    MissingAttribute {
+        element: String,
+        id: Option<String>, // None for elements which do not carry an id (i.e. <book>, <page>)
+        attribute: String,
+    },
+    InvalidAttribute {
+        element: String,
+        id: Option<String>,
+        attribute: String,
+        value: String,
+    },
+    EmptyText {
+        id: String,
+    },
+    // not a hexadecimal id, see AnnotationId
+    InvalidId(String),
+
+    // pages and images
+    NoPages(String),
+    PageOutOfRange {
+        book: String,
+        page: usize,
+    },
+    MissingImage(PathBuf),
+    // the image exists but could not be decoded (image feature only)
+    ImageDecode {
+        path: PathBuf,
+        message: String,
+    },
+
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+fn fmt_element(element: &str, id: &Option<String>) -> String {
+    match id {
+        Some(id) => format!("<{} id=\"{}\">", element, id),
+        None => format!("<{}>", element),
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingRootDir(path) => {
+                write!(f, "Root directory '{}' not found", path.display())
+            }
+            Error::MissingAnnotationsDir(path) => {
+                write!(f, "Annotations directory '{}' not found", path.display())
+            }
+            Error::MissingImagesDir(path) => {
+                write!(f, "Images directory '{}' not found", path.display())
+            }
+            Error::EmptyBookList(path) => {
+                write!(f, "No book titles found in '{}'", path.display())
+            }
+            Error::MissingBook(title) => write!(f, "Book '{}' not found", title),
+            Error::MissingAnnotationFile(path) => {
+                write!(f, "Annotation XML file '{}' not found", path.display())
+            }
+            Error::MissingBookImageDir(path) => {
+                write!(
+                    f,
+                    "Images directory '{}' for book not found",
+                    path.display()
+                )
+            }
+            Error::Xml {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path, line, column, message),
+            Error::MissingAttribute {
+                element,
+                id,
+                attribute,
+            } => write!(
+                f,
+                "{}: missing attribute '{}'",
+                fmt_element(element, id),
+                attribute
+            ),
+            Error::InvalidAttribute {
+                element,
+                id,
+                attribute,
+                value,
+            } => write!(
+                f,
+                "{}: attribute '{}' has invalid value '{}'",
+                fmt_element(element, id),
+                attribute,
+                value
+            ),
+            Error::InvalidId(id) => write!(f, "'{}' is not a hexadecimal id", id),
+            Error::EmptyText { id } => write!(f, "<text id=\"{}\"> has no text", id),
+            Error::NoPages(title) => write!(f, "No pages found in book '{}'", title),
+            Error::PageOutOfRange { book, page } => {
+                write!(f, "Page {} of book '{}' not found", page, book)
+            }
+            Error::MissingImage(path) => write!(f, "Image '{}' not found", path.display()),
+            Error::ImageDecode { path, message } => {
+                write!(
+                    f,
+                    "Unable to decode image '{}': {}",
+                    path.display(),
+                    message
+                )
+            }
+            Error::Io { path, source } => write!(f, "'{}': {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{Book, Error, Manga109, Page, Result};
+
+// A single line of the JSONL export; the page is flattened so that each line reads as
+//  {"title":"ARMS","index":3,"width":1654,"height":1170,"frames":[...],"texts":[...],...}
+// which is self-contained enough for notebooks/labelers to consume one page at a time
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PageRecord {
+    pub title: String,
+    #[serde(flatten)]
+    pub page: Page,
+}
+
+// Writes one PageRecord per line (in book order, then page order)
+pub fn write_jsonl<'a, W: Write>(
+    writer: &mut W,
+    books: impl IntoIterator<Item = &'a Book>,
+) -> std::result::Result<(), serde_json::Error> {
+    for book in books {
+        for page in book.pages.iter() {
+            // PageRecord owns its page, so borrow-serialize the same shape instead of cloning each page
+            #[derive(serde::Serialize)]
+            struct PageRecordRef<'b> {
+                title: &'b str,
+                #[serde(flatten)]
+                page: &'b Page,
+            }
+            serde_json::to_writer(
+                &mut *writer,
+                &PageRecordRef {
+                    title: &book.title,
+                    page,
+                },
+            )?;
+            writer.write_all(b"\n").map_err(serde_json::Error::io)?;
+        }
+    }
+    Ok(())
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> Error {
+    Error::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn create_file(path: &Path) -> Result<BufWriter<std::fs::File>> {
+    std::fs::File::create(path)
+        .map(BufWriter::new)
+        .map_err(|e| io_error(path, e))
+}
+
+impl Manga109 {
+    // Writes all the books as a single JSON array of Book (loading any book not loaded yet)
+    pub fn export_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let books = self.load_all()?;
+        let mut writer = create_file(path)?;
+        serde_json::to_writer(&mut writer, &books).map_err(|e| io_error(path, e.into()))?;
+        writer.flush().map_err(|e| io_error(path, e))
+    }
+
+    // Writes one page per line, see PageRecord
+    pub fn export_jsonl<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let books = self.load_all()?;
+        let mut writer = create_file(path)?;
+        write_jsonl(&mut writer, books).map_err(|e| io_error(path, e.into()))?;
+        writer.flush().map_err(|e| io_error(path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_jsonl() {
+        let xml = r#"
+            <book title="title">
+                <characters><character id="00000001" name="name1"/></characters>
+                <pages>
+                    <page index="0" width="100" height="200"/>
+                    <page index="1" width="100" height="200">
+                        <text id="00000002" xmin="10" ymin="20" xmax="30" ymax="40">value</text>
+                        <face id="00000003" xmin="10" ymin="20" xmax="30" ymax="40" character="00000001"/>
+                        <text id="00000004" xmin="50" ymin="20" xmax="70" ymax="40">value2</text>
+                    </page>
+                </pages>
+            </book>
+        "#;
+        let book = crate::parse_raw_xml_annotations_and_make_book(xml, "", "").unwrap();
+
+        let mut out = Vec::new();
+        write_jsonl(&mut out, [&book]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let record: PageRecord = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(record.title, "title");
+        assert_eq!(record.page.index, 1);
+        assert_eq!(record.page.texts, book.pages[1].texts);
+
+        // and the whole Book survives a JSON round-trip (minus the local file paths)
+        let json = serde_json::to_string(&book).unwrap();
+        let parsed: Book = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, book);
+        assert_eq!(parsed.get_annotation_file_paths(), "");
+        // the document order is not part of the format
+        assert!(!json.contains("order"));
+        let ids: Vec<String> = parsed.pages[1]
+            .elements()
+            .iter()
+            .map(|element| element.id().to_string())
+            .collect();
+        assert_eq!(ids, vec!["00000002", "00000004", "00000003"]);
+    }
+}
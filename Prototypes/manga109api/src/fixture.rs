@@ -0,0 +1,519 @@
+// Generates a fake (but structurally faithful) Manga109 dataset root, so that the tools can be
+// tested without the licensed dataset:
+//  root_dir
+//  ├── books.txt
+//  ├── annotations
+//  │   ├── Synthetic000.xml
+//  ├── images
+//  │   ├── Synthetic000
+//  │   │   ├── 000.jpg
+// Each page is a column of frames; in every frame the (vertical) texts are lined up from the
+// right and the characters (a body, with the face at the top of it) from the left.  The JPEGs
+// show the same boxes the XML describes: frames as black borders, bodies and faces as colored
+// boxes, texts as white balloons with the characters of the text in them.
+// The characters are drawn with a small bitmap font (FONT, 16x16 glyphs of the ALPHABET) scaled
+// to the width of the balloon, so that an OCR has something to read; a balloon is at most 48
+// pixels wide, the narrow ones (down to MIN_TEXT_WIDTH) are only good for the plumbing.
+// Frames which do not fit on the page (i.e. a large frames_per_page) are left out, so every box
+// is inside the page and the XML parses without a single defect.
+// Everything is derived from FixtureOptions::seed, so the same options give the same dataset.
+use std::path::Path;
+
+use image::{Rgb, RgbImage};
+
+use crate::{Body, Book, Character, Error, Face, Frame, Page, PageElement, Result, Text};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixtureOptions {
+    pub books: usize,
+    pub pages_per_book: usize,
+    pub frames_per_page: usize,
+    pub texts_per_frame: usize,
+    pub faces_per_frame: usize,     // each with its body
+    pub characters_per_book: usize, // faces/bodies are spread over these
+    pub page_width: u32,
+    pub page_height: u32,
+    pub seed: u64,
+}
+impl Default for FixtureOptions {
+    fn default() -> Self {
+        FixtureOptions {
+            books: 2,
+            pages_per_book: 4,
+            frames_per_page: 3,
+            texts_per_frame: 2,
+            faces_per_frame: 1,
+            characters_per_book: 3,
+            // the size of a single (not a spread) Manga109 page
+            page_width: 827,
+            page_height: 1170,
+            seed: 109,
+        }
+    }
+}
+
+const MARGIN: u32 = 24; // page margin, as well as the gap between frames
+const PADDING: u32 = 8; // between a frame border and what is in it
+const MIN_FRAME_SIZE: u32 = 16;
+const MIN_TEXT_WIDTH: u32 = 11; // a glyph (box width - 6) of at least 5x5
+const MAX_TEXT_LENGTH: usize = 8;
+const ALPHABET: &str =
+    "あいうえおかきくけこさしすせそたちつてとなにぬねのアイウエオカキクケコ人大小日本漫画？！ー";
+
+// xorshift64*, good enough for laying out boxes and not worth a dependency
+struct Rng(u64);
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng((seed ^ 0x9e37_79b9_7f4a_7c15) | 1)
+    }
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    // in lo..=hi
+    fn range(&mut self, lo: u32, hi: u32) -> u32 {
+        if hi <= lo {
+            return lo;
+        }
+        lo + (self.next() % (hi - lo + 1) as u64) as u32
+    }
+}
+
+// Book titles of the generated dataset, as listed in its books.txt
+pub fn titles(options: &FixtureOptions) -> Vec<String> {
+    (0..options.books)
+        .map(|book| format!("Synthetic{:03}", book))
+        .collect()
+}
+
+// Writes books.txt, annotations/<title>.xml and images/<title>/NNN.jpg under root_dir (which is
+// created if need be; files of an earlier run are overwritten)
+pub fn generate<P: AsRef<Path>>(root_dir: P, options: &FixtureOptions) -> Result<()> {
+    let root_dir = root_dir.as_ref();
+    let mut rng = Rng::new(options.seed);
+    let mut next_id = 0u32;
+    let titles = titles(options);
+    for title in titles.iter() {
+        let image_dir = root_dir.join("images").join(title);
+        create_dir_all(&image_dir)?;
+        let (book, images) = generate_book(title, options, &mut rng, &mut next_id);
+        for (page, image) in book.pages.iter().zip(images) {
+            let path = image_dir.join(format!("{:03}.jpg", page.index));
+            image.save(&path).map_err(|e| Error::Io {
+                path: path.clone(),
+                source: std::io::Error::other(e),
+            })?;
+        }
+        let annotations_dir = root_dir.join("annotations");
+        create_dir_all(&annotations_dir)?;
+        book.write_xml(annotations_dir.join(format!("{}.xml", title)))?;
+    }
+    let books_txt = root_dir.join("books.txt");
+    std::fs::write(&books_txt, titles.join("\n") + "\n").map_err(|e| Error::Io {
+        path: books_txt,
+        source: e,
+    })
+}
+
+fn create_dir_all(path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path).map_err(|e| Error::Io {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+fn generate_book(
+    title: &str,
+    options: &FixtureOptions,
+    rng: &mut Rng,
+    next_id: &mut u32,
+) -> (Book, Vec<RgbImage>) {
+    let mut new_id = || {
+        *next_id += 1;
+        format!("{:08x}", next_id)
+    };
+    let characters: Vec<Character> = (0..options.characters_per_book)
+        .map(|character| Character {
+            id: new_id(),
+            name: format!("Character {}", character + 1),
+        })
+        .collect();
+
+    let (width, height) = (options.page_width, options.page_height);
+    let mut pages = Vec::new();
+    let mut images = Vec::new();
+    for index in 0..options.pages_per_book {
+        let mut page = Page::new(index, width, height);
+        let mut image = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+        // as many of the frames as fit on the page, each at least MIN_FRAME_SIZE
+        let fitting_frames = if width >= 2 * MARGIN + MIN_FRAME_SIZE {
+            height.saturating_sub(MARGIN) / (MIN_FRAME_SIZE + MARGIN)
+        } else {
+            0
+        };
+        let frames = (options.frames_per_page as u32).min(fitting_frames);
+        let frame_height = height.saturating_sub(MARGIN * (frames + 1)) / frames.max(1);
+        for row in 0..frames {
+            let frame = Frame {
+                id: new_id(),
+                xmin: MARGIN,
+                ymin: MARGIN + row * (frame_height + MARGIN),
+                xmax: width - MARGIN,
+                ymax: MARGIN + row * (frame_height + MARGIN) + frame_height,
+            };
+            draw_border(&mut image, &frame, 3, Rgb([0, 0, 0]));
+            fill_frame(
+                &mut page,
+                &mut image,
+                &frame,
+                options,
+                &characters,
+                rng,
+                &mut new_id,
+            );
+            page.push(PageElement::Frame(frame));
+        }
+        pages.push(page);
+        images.push(image);
+    }
+
+    let mut book = Book {
+        characters,
+        pages,
+        title: title.to_string(),
+        annotation_filepaths: String::new(),
+        image_root_dir: String::new(),
+        page_positions: Default::default(),
+    };
+    book.index_pages();
+    (book, images)
+}
+
+// Splits the frame into columns, texts on the right and characters on the left
+fn fill_frame(
+    page: &mut Page,
+    image: &mut RgbImage,
+    frame: &Frame,
+    options: &FixtureOptions,
+    characters: &[Character],
+    rng: &mut Rng,
+    new_id: &mut impl FnMut() -> String,
+) {
+    let columns = (options.texts_per_frame + options.faces_per_frame) as u32;
+    let inner_width = (frame.xmax - frame.xmin).saturating_sub(2 * PADDING);
+    let inner_height = (frame.ymax - frame.ymin).saturating_sub(2 * PADDING);
+    if columns == 0 || inner_width < columns * 8 || inner_height < 16 {
+        return; // too small to put anything in
+    }
+    let column_width = inner_width / columns;
+    let (top, bottom) = (frame.ymin + PADDING, frame.ymax - PADDING);
+
+    for column in 0..options.texts_per_frame as u32 {
+        if column_width < MIN_TEXT_WIDTH {
+            break; // a narrow page, no room for a text box
+        }
+        let right = frame.xmax - PADDING - column * column_width;
+        let box_width = rng
+            .range(column_width / 3, column_width * 2 / 3)
+            .clamp(MIN_TEXT_WIDTH, 48);
+        let glyph = box_width - 6;
+        let max_length = ((inner_height - 6) / glyph) as usize;
+        if max_length == 0 {
+            continue; // not even a single character fits (i.e. a very flat frame)
+        }
+        let length = rng.range(1, max_length.min(MAX_TEXT_LENGTH) as u32) as usize;
+        let alphabet: Vec<char> = ALPHABET.chars().collect();
+        let value: String = (0..length)
+            .map(|_| alphabet[rng.range(0, alphabet.len() as u32 - 1) as usize])
+            .collect();
+        let box_height = length as u32 * glyph + 6;
+        let xmax = right - rng.range(0, column_width - box_width);
+        let ymin = top + rng.range(0, inner_height - box_height);
+        let text = Text {
+            id: new_id(),
+            xmin: xmax - box_width,
+            ymin,
+            xmax,
+            ymax: ymin + box_height,
+            value,
+        };
+        draw_text(image, &text, glyph);
+        page.push(PageElement::Text(text));
+    }
+
+    for column in 0..options.faces_per_frame as u32 {
+        if characters.is_empty() {
+            break;
+        }
+        let character = &characters[rng.range(0, characters.len() as u32 - 1) as usize];
+        let left = frame.xmin + PADDING + column * column_width;
+        let body = Body {
+            id: new_id(),
+            xmin: left + 2,
+            ymin: top + rng.range(0, inner_height / 4),
+            xmax: left + column_width - 2,
+            ymax: bottom,
+            character: character.id.clone(),
+        };
+        let size = (body.xmax - body.xmin)
+            .min((body.ymax - body.ymin) / 2)
+            .saturating_sub(4)
+            .max(1);
+        let face_left = body.xmin + (body.xmax - body.xmin - size) / 2;
+        let face = Face {
+            id: new_id(),
+            xmin: face_left,
+            ymin: body.ymin + 2,
+            xmax: face_left + size,
+            ymax: body.ymin + 2 + size,
+            character: character.id.clone(),
+        };
+        fill(image, &body, Rgb([200, 210, 240]));
+        draw_border(image, &body, 1, Rgb([60, 60, 120]));
+        fill(image, &face, Rgb([255, 224, 196]));
+        draw_border(image, &face, 1, Rgb([120, 60, 60]));
+        page.push(PageElement::Body(body));
+        page.push(PageElement::Face(face));
+    }
+}
+
+fn fill(image: &mut RgbImage, element: &impl crate::HasBoundingBox, color: Rgb<u8>) {
+    let bbox = element.bounding_box().clamp(image.width(), image.height());
+    for y in bbox.ymin..bbox.ymax {
+        for x in bbox.xmin..bbox.xmax {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+fn draw_border(
+    image: &mut RgbImage,
+    element: &impl crate::HasBoundingBox,
+    thickness: u32,
+    color: Rgb<u8>,
+) {
+    let bbox = element.bounding_box().clamp(image.width(), image.height());
+    for y in bbox.ymin..bbox.ymax {
+        for x in bbox.xmin..bbox.xmax {
+            if x < bbox.xmin + thickness
+                || x + thickness >= bbox.xmax
+                || y < bbox.ymin + thickness
+                || y + thickness >= bbox.ymax
+            {
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+// A white balloon, with the characters top to bottom in FONT, scaled (nearest neighbour) from
+// 16x16 to glyph x glyph pixels
+fn draw_text(image: &mut RgbImage, text: &Text, glyph: u32) {
+    fill(image, text, Rgb([255, 255, 255]));
+    draw_border(image, text, 1, Rgb([0, 0, 0]));
+    let left = text.xmin + 3;
+    for (position, c) in text.value.chars().enumerate() {
+        let top = text.ymin + 3 + position as u32 * glyph;
+        let Some(rows) = font_glyph(c) else {
+            continue;
+        };
+        for y in 0..glyph {
+            let row = rows[(y * 16 / glyph) as usize];
+            for x in 0..glyph {
+                if row & (0x8000 >> (x * 16 / glyph)) != 0 {
+                    image.put_pixel(left + x, top + y, Rgb([0, 0, 0]));
+                }
+            }
+        }
+    }
+}
+
+// Rows of the 16x16 bitmap of c, the most significant bit on the left; None if FONT lacks it
+fn font_glyph(c: char) -> Option<[u16; 16]> {
+    let code_point = format!("{:04X}:", c as u32);
+    let hex = FONT
+        .lines()
+        .find_map(|line| line.strip_prefix(&code_point))?;
+    let mut rows = [0u16; 16];
+    for (row, bits) in rows.iter_mut().enumerate() {
+        *bits = u16::from_str_radix(hex.get(row * 4..row * 4 + 4)?, 16).ok()?;
+    }
+    Some(rows)
+}
+
+// The characters of ALPHABET, hand drawn on a 16x16 grid (ー in its vertical writing form, as
+// the texts are vertical), in the GNU Unifont .hex format: the code point, then 16 rows of 4 hex
+// digits each
+const FONT: &str = "\
+3042:0000080008007FE0080009E00B181E042A044904490448883050002000000000
+3044:0000200020002008200420022002200220022000100010800900060000000000
+3046:000007C0000000001FC020300008000800080010002000C00700000000000000
+3048:000007C0000000001FF000400080010003F0041008101010200E000000000000
+304A:0000040004187FC4040004F007080C041404240424081C3007C0000000000000
+304B:0000040004087FC4044204400840084010401040214040C00000000000000000
+304D:000002003F8001007FE0008000400FF010001000080007F00000000000000000
+304F:0000004000800100020004000800040002000100008000400000000000000000
+3051:00002020202023FC202020202020202020202020104010800100000000000000
+3053:000000001FF000100000000000000000200020001FF800000000000000000000
+3055:0000020001007FE0008000400FE010001000080007F000000000000000000000
+3057:0000100010001000100010001000100010081010082007C00000000000000000
+3059:000001007FFC0100078008800880078000800100020004000000000000000000
+305B:0000082008207FF80820082008C008000800080007F000000000000000000000
+305D:00001FC00040008001007FF80100020002000200010000F00000000000000000
+305F:0000040004007F80080008FC0800100010002080207E00000000000000000000
+3061:000002007FE0040004000BF00C08100800080010006003800000000000000000
+3064:00000000000000007F0000E00010000800080010006003800000000000000000
+3066:000000007FF8008001000200020002000200010000F800000000000000000000
+3068:0000080004000408023002C006000800100010000FF800000000000000000000
+306A:000004007F900808080010401040204023C00460045003880000000000000000
+306B:0000200023F8200020002000200020002200120011FC00000000000000000000
+306C:00000200210023C024A028903088208831084A3C444A02320000000000000000
+306D:0000100010007BC01420181030105010101010F0111810E40000000000000000
+306E:0000000003C00CB0108821042104220422042408181000600000000000000000
+30A2:00007FF800100020024002800200040004000800100020000000000000000000
+30A4:0000001000200040008003800880208000800080008000800000000000000000
+30A6:0000010001003FF8200820082010001000200040018006000000000000000000
+30A8:000000003FF80100010001000100010001007FFC000000000000000000000000
+30AA:0000004000407FFC004000C00140024004401840604001C00000000000000000
+30AB:0000020002007FF8020802080408040808081008209040600000000000000000
+30AD:0000010001003FF8010001007FFC008000800080008000800000000000000000
+30AF:0000020007F00810101020200020004000800100060018000000000000000000
+30B1:0000040008001FFE202040200020004000800100060018000000000000000000
+30B3:000000003FF00010001000100010001000103FF0000000000000000000000000
+30FC:0000010001000100010001000100010001000100010001000000000000000000
+4EBA:00000100010001000100010002800280044008201010600C0000000000000000
+5927:00000100010001007FFC010002800280044008201010600C0000000000000000
+5C0F:0000010001000100111011082104210241000100010007000000000000000000
+65E5:00001FF010101010101010101FF010101010101010101FF00000000000000000
+672C:0000010001007FFC038005400540092011102FE8410401000000000000000000
+6F2B:000043F8220803F8820843F80FFC29242FFC400047FC8208811000E0071C0000
+753B:00007FFE00004FF9488948894FF9488948894FF940017FFF0000000000000000
+FF01:0000010001000100010001000100010000000100000000000000000000000000
+FF1F:000007C008200820002000400080010001000000010000000000000000000000
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate::validate, AnnotationVersion, HasBoundingBox, Manga109};
+
+    #[test]
+    fn test_generate() {
+        let root = std::env::temp_dir().join(format!("manga109api_fixture_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let options = FixtureOptions {
+            pages_per_book: 2,
+            page_width: 400,
+            page_height: 600,
+            ..Default::default()
+        };
+        generate(&root, &options).unwrap();
+
+        // a dataset the validator has nothing to say about (XML, ids and JPEG sizes all agree)
+        let issues = validate(root.to_str().unwrap(), &AnnotationVersion::Latest).unwrap();
+        assert!(issues.is_empty(), "{:?}", issues);
+
+        let manga109 = Manga109::new(root.to_str().unwrap()).unwrap();
+        assert_eq!(manga109.titles(), titles(&options));
+        let books = manga109.load_all().unwrap();
+        let page = &books[1].pages[1];
+        assert_eq!((page.width, page.height), (400, 600));
+        assert_eq!(page.frames.len(), 3);
+        assert_eq!(page.texts.len(), 6);
+        assert_eq!((page.faces.len(), page.bodies.len()), (3, 3));
+        for located in manga109.query().unwrap().texts() {
+            assert!(located.page.frames.iter().any(|frame| {
+                located.element.xmin >= frame.xmin
+                    && located.element.xmax <= frame.xmax
+                    && located.element.ymin >= frame.ymin
+                    && located.element.ymax <= frame.ymax
+            }));
+        }
+        // the same options give the same dataset
+        let xml = std::fs::read_to_string(books[0].get_annotation_file_paths()).unwrap();
+        let again = root.join("again");
+        generate(&again, &options).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(again.join("annotations/Synthetic000.xml")).unwrap(),
+            xml
+        );
+
+        // more frames than fit on the page: the layout stays on the page
+        let crowded = root.join("crowded");
+        let options = FixtureOptions {
+            books: 1,
+            pages_per_book: 1,
+            frames_per_page: 50,
+            page_width: 200,
+            page_height: 300,
+            ..Default::default()
+        };
+        generate(&crowded, &options).unwrap();
+        let manga109 = Manga109::new(crowded.to_str().unwrap()).unwrap();
+        let page = &manga109.book("Synthetic000").unwrap().pages[0];
+        assert_eq!(page.frames.len(), 6); // (300 - 24) / (16 + 24)
+        let on_page = crate::BoundingBox::new(0, 0, page.width, page.height);
+        for element in page.elements() {
+            assert!(on_page.contains(&element.bounding_box()), "{:?}", element);
+        }
+        let issues = validate(crowded.to_str().unwrap(), &AnnotationVersion::Latest).unwrap();
+        assert!(issues.is_empty(), "{:?}", issues);
+
+        // narrow pages: texts get as narrow as MIN_TEXT_WIDTH, or are left out
+        for page_width in 60..=130 {
+            let options = FixtureOptions {
+                page_width,
+                page_height: 300,
+                ..Default::default()
+            };
+            let (book, _) =
+                generate_book("Narrow", &options, &mut Rng::new(page_width as u64), &mut 0);
+            let page = &book.pages[0];
+            // a column is (page_width - 2 * (MARGIN + PADDING)) / 3 wide
+            assert_eq!(page.texts.is_empty(), page_width < 97, "{}", page_width);
+            for text in page.texts.iter() {
+                assert!(text.xmax - text.xmin >= MIN_TEXT_WIDTH);
+                assert!(text.xmax <= page_width - MARGIN);
+            }
+        }
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_draw_text() {
+        for c in ALPHABET.chars() {
+            assert!(font_glyph(c).is_some(), "{} is not in FONT", c);
+        }
+        assert_eq!(font_glyph('A'), None);
+
+        // at 16 pixels a glyph is drawn as it is in FONT
+        let text = Text {
+            id: "00000001".to_string(),
+            xmin: 10,
+            ymin: 10,
+            xmax: 32,
+            ymax: 48,
+            value: "日本".to_string(),
+        };
+        let mut image = RgbImage::from_pixel(64, 64, Rgb([255, 255, 255]));
+        draw_text(&mut image, &text, 16);
+        for (position, c) in text.value.chars().enumerate() {
+            let rows = font_glyph(c).unwrap();
+            for y in 0..16 {
+                for x in 0..16 {
+                    let ink = image.get_pixel(13 + x, 13 + position as u32 * 16 + y)[0] == 0;
+                    assert_eq!(
+                        ink,
+                        rows[y as usize] & (0x8000 >> x) != 0,
+                        "{} {},{}",
+                        c,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::{Body, Book, Character, Error, Face, Frame, Page, Result, Text};
+
+// The ids of the annotation XML (characters, frames, texts, faces and bodies alike) are 8 digit,
+// zero-padded, lower-case hexadecimal numbers (i.e. "0000000b") which are unique across the whole
+// dataset.  AnnotationId keeps the number (so ids compare and sort numerically, and hash cheaply)
+// along with the number of digits it was written with, so that it formats back the way it was
+// read.  Equality, ordering and hashing only go by the number, so "b" == "0000000b".
+#[derive(Debug, Clone, Copy)]
+pub struct AnnotationId {
+    value: u64,
+    width: u8, // number of digits, as parsed
+}
+
+impl AnnotationId {
+    // an 8 digit id, as used throughout Manga109
+    pub fn new(value: u64) -> Self {
+        AnnotationId { value, width: 8 }
+    }
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+impl FromStr for AnnotationId {
+    type Err = Error;
+
+    // 1 to 16 hexadecimal digits, nothing else (no sign, prefix or whitespace)
+    fn from_str(id: &str) -> Result<Self> {
+        if id.is_empty() || id.len() > 16 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Error::InvalidId(id.to_string()));
+        }
+        let value = u64::from_str_radix(id, 16).map_err(|_| Error::InvalidId(id.to_string()))?;
+        Ok(AnnotationId {
+            value,
+            width: id.len() as u8,
+        })
+    }
+}
+
+impl fmt::Display for AnnotationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:0width$x}", self.value, width = self.width as usize)
+    }
+}
+
+impl PartialEq for AnnotationId {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+impl Eq for AnnotationId {}
+impl PartialOrd for AnnotationId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for AnnotationId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+impl Hash for AnnotationId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+// as the string it formats to, same as in the XML
+#[cfg(feature = "serde")]
+impl serde::Serialize for AnnotationId {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for AnnotationId {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        id.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Whatever an id of a book refers to; the page elements come with the page they are on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementRef<'a> {
+    Character(&'a Character),
+    Frame(&'a Page, &'a Frame),
+    Text(&'a Page, &'a Text),
+    Face(&'a Page, &'a Face),
+    Body(&'a Page, &'a Body),
+}
+impl<'a> ElementRef<'a> {
+    pub fn id(&self) -> &'a str {
+        match self {
+            ElementRef::Character(character) => &character.id,
+            ElementRef::Frame(_, frame) => &frame.id,
+            ElementRef::Text(_, text) => &text.id,
+            ElementRef::Face(_, face) => &face.id,
+            ElementRef::Body(_, body) => &body.id,
+        }
+    }
+    // None for a character
+    pub fn page(&self) -> Option<&'a Page> {
+        match self {
+            ElementRef::Character(_) => None,
+            ElementRef::Frame(page, _)
+            | ElementRef::Text(page, _)
+            | ElementRef::Face(page, _)
+            | ElementRef::Body(page, _) => Some(page),
+        }
+    }
+}
+
+impl Book {
+    // Everything with an id, characters first and then page by page in per-tag order
+    fn elements_with_id(&self) -> impl Iterator<Item = ElementRef<'_>> {
+        let characters = self.characters.iter().map(ElementRef::Character);
+        let pages = self.pages.iter().flat_map(|page| {
+            let frames = page.frames.iter().map(move |e| ElementRef::Frame(page, e));
+            let texts = page.texts.iter().map(move |e| ElementRef::Text(page, e));
+            let faces = page.faces.iter().map(move |e| ElementRef::Face(page, e));
+            let bodies = page.bodies.iter().map(move |e| ElementRef::Body(page, e));
+            frames.chain(texts).chain(faces).chain(bodies)
+        });
+        characters.chain(pages)
+    }
+
+    // The character or page element with this id (the first one, should the id be repeated); this
+    // is a scan of the whole book, see id_index() for many lookups
+    pub fn find_by_id(&self, id: AnnotationId) -> Option<ElementRef<'_>> {
+        self.elements_with_id()
+            .find(|element| element.id().parse::<AnnotationId>().ok() == Some(id))
+    }
+
+    // All the ids of the book (the first one wins, should an id be repeated); elements whose id is
+    // not hexadecimal cannot be looked up and are left out
+    pub fn id_index(&self) -> HashMap<AnnotationId, ElementRef<'_>> {
+        let mut index = HashMap::new();
+        for element in self.elements_with_id() {
+            if let Ok(id) = element.id().parse() {
+                index.entry(id).or_insert(element);
+            }
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_raw_xml_annotations_and_make_book;
+
+    #[test]
+    fn test_annotation_id() {
+        let id: AnnotationId = "0000000b".parse().unwrap();
+        assert_eq!(id.value(), 11);
+        assert_eq!(id.to_string(), "0000000b");
+        assert_eq!("B".parse::<AnnotationId>().unwrap(), id);
+        assert_eq!("b".parse::<AnnotationId>().unwrap().to_string(), "b");
+        assert_eq!(AnnotationId::new(0x1f).to_string(), "0000001f");
+        for invalid in ["", "id1", "+b", "0x0b", " 0b", "00000000000000000"] {
+            assert!(matches!(
+                invalid.parse::<AnnotationId>(),
+                Err(Error::InvalidId(_))
+            ));
+        }
+        // numerically, not lexicographically
+        let mut ids: Vec<AnnotationId> = ["a", "0000000b", "9", "10"]
+            .iter()
+            .map(|id| id.parse().unwrap())
+            .collect();
+        ids.sort();
+        let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        assert_eq!(ids, vec!["9", "a", "0000000b", "10"]);
+    }
+
+    #[test]
+    fn test_find_by_id() {
+        let book = parse_raw_xml_annotations_and_make_book(
+            r#"<book title="title">
+                <characters><character id="0000000a" name="Alice"/></characters>
+                <pages>
+                    <page index="0" width="100" height="200">
+                        <frame id="00000001" xmin="0" ymin="0" xmax="100" ymax="200"/>
+                    </page>
+                    <page index="1" width="100" height="200">
+                        <text id="00000002" xmin="10" ymin="20" xmax="30" ymax="40">はい</text>
+                        <face id="00000003" xmin="10" ymin="20" xmax="30" ymax="40" character="0000000a"/>
+                        <body id="not-hex" xmin="10" ymin="20" xmax="30" ymax="40" character="0000000a"/>
+                    </page>
+                </pages>
+            </book>"#,
+            "",
+            "",
+        )
+        .unwrap();
+        let id = |id: &str| id.parse::<AnnotationId>().unwrap();
+
+        match book.find_by_id(id("00000002")) {
+            Some(ElementRef::Text(page, text)) => {
+                assert_eq!(page.index, 1);
+                assert_eq!(text.value, "はい");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            book.find_by_id(id("a")),
+            Some(ElementRef::Character(character)) if character.name == "Alice"
+        ));
+        assert!(book.find_by_id(id("00000004")).is_none());
+
+        let index = book.id_index();
+        assert_eq!(index.len(), 4); // the body's id is not hexadecimal
+        assert_eq!(index[&id("00000001")].page().unwrap().index, 0);
+        assert_eq!(index[&id("00000003")].id(), "00000003");
+        let texts: HashMap<AnnotationId, &Text> = book.pages[1]
+            .texts
+            .iter()
+            .map(|text| (text.id.parse().unwrap(), text))
+            .collect();
+        assert_eq!(texts[&id("2")].value, "はい");
+    }
+}
@@ -1,23 +1,79 @@
-use anyhow::Result;
-use roxmltree;
+mod bbox;
+#[cfg(feature = "cache")]
+mod cache;
+mod character_index;
+mod diagnostic;
+pub mod diff;
+mod error;
+#[cfg(feature = "serde")]
+mod export;
+#[cfg(feature = "fixture")]
+pub mod fixture;
+mod id;
+#[cfg(feature = "image")]
+mod page_image;
+mod panel;
+mod query;
+mod reader;
+pub mod reading_order;
+mod speaker;
+pub mod stats;
+pub mod validate;
+mod version;
+mod writer;
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+pub use bbox::{BoundingBox, HasBoundingBox};
+#[cfg(feature = "cache")]
+pub use cache::{CACHE_DIR_NAME, NO_CACHE_ENV};
+pub use character_index::{Appearance, CastMember, CharacterIndex};
+pub use diagnostic::{Defect, ParseAction, ParseDiagnostic, ParseOptions};
+pub use error::{Error, Result};
+#[cfg(feature = "serde")]
+pub use export::{write_jsonl, PageRecord};
+pub use id::{AnnotationId, ElementRef};
+#[cfg(feature = "image")]
+pub use page_image::{PageImage, DEFAULT_IMAGE_CACHE_CAPACITY};
+pub use panel::{FrameTexts, TextAssignment, DEFAULT_MIN_OVERLAP_RATIO};
+pub use query::{Located, Query};
+pub use reader::{BookReader, Pages};
+pub use speaker::{SpeakerAttribution, SpeakerCandidate};
+pub use version::AnnotationVersion;
 
 // annotation_tags = ["frame", "face", "body", "text"]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AnnotationType {
     Frame,
     Face,
     Body,
     Text,
 }
+impl AnnotationType {
+    // XML tag name
+    pub fn tag(&self) -> &'static str {
+        match self {
+            AnnotationType::Frame => "frame",
+            AnnotationType::Face => "face",
+            AnnotationType::Body => "body",
+            AnnotationType::Text => "text",
+        }
+    }
+}
 
 fn normalize_paths(path: &str) -> String {
-    path.replace("\\", "/").replace("//", "/")
+    path.replace('\\', "/").replace("//", "/")
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Annotation {
     pub title: String,
     pub characters: Vec<Character>,
-    pub pages: Vec<Page>,
+    pub pages: Vec<Page>,            // separate_by_tag == true
+    pub contents: Vec<PageContents>, // separate_by_tag == false
 }
 impl Annotation {
     pub fn new(title: String) -> Self {
@@ -25,14 +81,37 @@ impl Annotation {
             title,
             characters: Vec::new(),
             pages: Vec::new(),
+            contents: Vec::new(),
         }
     }
 }
 
+// A page as returned by get_annotation() with separate_by_tag == false: all the elements in one
+// list, in the same order as in the original XML file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageContents {
+    pub index: usize,
+    pub width: u32,
+    pub height: u32,
+    pub contents: Vec<PageElement>,
+}
+
 #[derive(Debug)]
 pub struct Manga109 {
     pub root_dir: String,
-    pub books: Vec<Book>,
+    titles: Vec<String>, // as listed in books.txt
+    annotation_version: AnnotationVersion,
+    options: ParseOptions,
+    // one slot per title (same order as `titles`), each book is parsed on first access; the
+    // diagnostics are the defects repaired/skipped while parsing in lenient mode
+    books: Vec<OnceLock<(Book, Vec<ParseDiagnostic>)>>,
+    // recently decoded page images, see load_page_image()
+    #[cfg(feature = "image")]
+    image_cache: std::sync::Mutex<page_image::ImageCache>,
+    // where the parsed books are cached, None to always parse the XML (see cache.rs)
+    #[cfg(feature = "cache")]
+    cache_dir: Option<std::path::PathBuf>,
 }
 impl Manga109 {
     //  Manga109 annotation parser
@@ -42,7 +121,25 @@ impl Manga109 {
     //  self.books = []  # book titles
     //  with (self.root_dir / "books.txt").open("rt", encoding='utf-8') as f:
     //      self.books = [line.rstrip() for line in f]
-    pub fn new(root_dir: &str) -> Self {
+    // NOTE: unlike the earlier versions, this does NOT parse any of the annotation XML files, books
+    // are parsed on first access via book() (or all at once via load_all())
+    pub fn new(root_dir: &str) -> Result<Self> {
+        Self::new_with_options(root_dir, &ParseOptions::default())
+    }
+
+    // Same as new(), but with ParseOptions::lenient() the whole dataset can be loaded even if some
+    // of the annotations are defective; see diagnostics() for what has been repaired or skipped
+    pub fn new_with_options(root_dir: &str, options: &ParseOptions) -> Result<Self> {
+        Self::new_with_version(root_dir, &AnnotationVersion::Latest, options)
+    }
+
+    // Same as new_with_options(), but reads the XML files from the annotation directory of the given
+    // version (i.e. "annotations.v2018.05.31/") rather than the latest "annotations/"
+    pub fn new_with_version(
+        root_dir: &str,
+        annotation_version: &AnnotationVersion,
+        options: &ParseOptions,
+    ) -> Result<Self> {
         // first, make sure we can find "books.txt" here, it is just a CASE-SENSITIVE (per-line) list
         // of book titles which matches the images sub-directory names as well as annotations XML files
         // For example:
@@ -56,27 +153,160 @@ impl Manga109 {
         // ├── annotations
         // │   ├── book1.xml    // xml filename matches the book title in books.txt
         // │   ├── ...
-        let title_list: Vec<_> = std::fs::read_to_string(format!("{}/books.txt", root_dir))
-            .expect("Could not read books.txt")
+        // ├── annotations.v2020.12.18  // older releases of the annotations, see AnnotationVersion
+        // ├── annotations.v2018.05.31
+        let root_path = std::path::Path::new(root_dir);
+        if !root_path.exists() {
+            return Err(Error::MissingRootDir(root_dir.into()));
+        }
+        let books_txt = root_path.join("books.txt");
+        let title_list: Vec<_> = std::fs::read_to_string(&books_txt)
+            .map_err(|e| Error::Io {
+                path: books_txt.clone(),
+                source: e,
+            })?
             .lines()
             .map(|line| line.to_string())
             .collect();
-        // if 0 book, panic!
+        // 0 books is an error rather than an empty Manga109
         if title_list.is_empty() {
-            panic!("No book titles found in books.txt");
+            return Err(Error::EmptyBookList(books_txt));
+        }
+
+        // validate the layout up-front (cheap, no parsing), so that a missing XML or image
+        // directory is reported now rather than half-way through a long running job
+        let annotations_root_dir = root_path.join(annotation_version.dir_name());
+        if !annotations_root_dir.exists() {
+            return Err(Error::MissingAnnotationsDir(annotations_root_dir));
+        }
+        let images_root_dir = root_path.join("images");
+        if !images_root_dir.exists() {
+            return Err(Error::MissingImagesDir(images_root_dir));
         }
-        // Now that books.txt is read, walk through each book titles listed in the file
-        let mut books = Vec::new();
         for title in title_list.iter() {
-            // For each book title, parse the corresponding XML file
-            let book = get_book(root_dir, title).expect("Could not parse XML file");
-            books.push(book);
+            let xml_path = annotations_root_dir.join(format!("{}.xml", title));
+            if !xml_path.exists() {
+                return Err(Error::MissingAnnotationFile(xml_path));
+            }
+            let image_dir = images_root_dir.join(title);
+            if !image_dir.exists() {
+                return Err(Error::MissingBookImageDir(image_dir));
+            }
         }
 
-        Manga109 {
+        Ok(Manga109 {
             root_dir: root_dir.to_string(),
-            books: books, // 1 or more books, if it's empty, we should panic!
+            books: title_list.iter().map(|_| OnceLock::new()).collect(),
+            titles: title_list, // 1 or more books
+            annotation_version: annotation_version.clone(),
+            options: *options,
+            #[cfg(feature = "image")]
+            image_cache: std::sync::Mutex::new(page_image::ImageCache::new(
+                DEFAULT_IMAGE_CACHE_CAPACITY,
+            )),
+            #[cfg(feature = "cache")]
+            cache_dir: cache::default_cache_dir(root_dir),
+        })
+    }
+
+    // Always parse the XML files, even with the "cache" feature (see cache.rs)
+    pub fn without_cache(self) -> Self {
+        Manga109 {
+            #[cfg(feature = "cache")]
+            cache_dir: None,
+            ..self
+        }
+    }
+
+    pub fn annotation_version(&self) -> &AnnotationVersion {
+        &self.annotation_version
+    }
+
+    // Book titles in the order they are listed in books.txt
+    pub fn titles(&self) -> &[String] {
+        &self.titles
+    }
+
+    // Returns the (parsed) book, parsing its annotation XML on first access
+    pub fn book(&self, title: &str) -> Result<&Book> {
+        let slot = self
+            .titles
+            .iter()
+            .position(|t| t == title)
+            .ok_or_else(|| Error::MissingBook(title.to_string()))?;
+        self.load(slot)
+    }
+
+    fn load(&self, slot: usize) -> Result<&Book> {
+        let cell = &self.books[slot];
+        if let Some((book, _)) = cell.get() {
+            return Ok(book);
+        }
+        // if two threads race here, both parse but only the first one gets stored (same result)
+        #[cfg(feature = "cache")]
+        if let Some(cache_dir) = &self.cache_dir {
+            let parsed = cache::get_book_cached(
+                cache_dir,
+                &self.root_dir,
+                &self.titles[slot],
+                &self.annotation_version,
+                &self.options,
+            )?;
+            return Ok(&cell.get_or_init(|| parsed).0);
         }
+        let parsed = get_book_with_options(
+            &self.root_dir,
+            &self.titles[slot],
+            &self.annotation_version,
+            &self.options,
+        )?;
+        Ok(&cell.get_or_init(|| parsed).0)
+    }
+
+    // Parses every book which has not been loaded yet (this is what new() used to do), returning
+    // all the books in books.txt order, or the first (in books.txt order) error
+    pub fn load_all(&self) -> Result<Vec<&Book>> {
+        self.load_all_results()
+            .into_iter()
+            .map(|(_, result)| result)
+            .collect()
+    }
+
+    // Same as load_all(), but a book which fails to parse does not stop the rest from loading;
+    // every title in books.txt gets its own result (in books.txt order).  With the "rayon"
+    // feature, the books are parsed concurrently.
+    pub fn load_all_results(&self) -> Vec<(&str, Result<&Book>)> {
+        #[cfg(feature = "rayon")]
+        let results: Vec<_> = {
+            use rayon::prelude::*;
+            (0..self.titles.len())
+                .into_par_iter()
+                .map(|slot| self.load(slot))
+                .collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let results: Vec<_> = (0..self.titles.len()).map(|slot| self.load(slot)).collect();
+
+        self.titles
+            .iter()
+            .map(|title| title.as_str())
+            .zip(results)
+            .collect()
+    }
+
+    // Books which have been loaded (parsed) so far, in books.txt order
+    pub fn loaded_books(&self) -> impl Iterator<Item = &Book> {
+        self.books
+            .iter()
+            .filter_map(|cell| cell.get().map(|(book, _)| book))
+    }
+
+    // Defects repaired/skipped (lenient mode only) in the books which have been loaded so far
+    pub fn diagnostics(&self) -> impl Iterator<Item = &ParseDiagnostic> {
+        self.books
+            .iter()
+            .filter_map(|cell| cell.get())
+            .flat_map(|(_, diagnostics)| diagnostics.iter())
     }
 
     // Given a book title and an index of a page, return the correct image path
@@ -90,36 +320,28 @@ impl Manga109 {
     // assert book in self.books
     // assert isinstance(index, int)
     // return str((self.root_dir / "images" / book / (str(index).zfill(3) + ".jpg")).resolve())  // note: 3 digits jpg file
+    //
+    // NOTE: page_index is the annotated <page index="N">, which is also what the JPEG is named
+    // after; it is not the position in Book::pages (pages can be skipped or out of order)
     pub fn img_path(&self, book: &str, page_index: &usize) -> Result<String> {
-        let possible_book = self.books.iter().find(|b| b.title == book);
-        // if cannot find, panic
-        if possible_book.is_none() {
-            panic!("Book not found");
-        }
-        let book = possible_book.expect("Book '{book}' not found");
-        let (index, page) = book
-            .pages
-            .iter()
-            .enumerate()
-            .find(|(idx, pg)| idx == page_index)
-            .expect("Page {page} of book '{book}' not found");
-        let debug_image_root_dir = format!("{}/images/{}", self.root_dir, book.title); // won't use this in the future...
-        if debug_image_root_dir != book.get_image_dir_paths() {
-            // sometimes, on Windows path, it might have \\ instead of / so it's good to know when/where I've violated the pathing format...
-            println!(
-                "WARNING: Image root directory mismatch - expected:{}, actual:{}",
-                debug_image_root_dir,
-                book.get_image_dir_paths()
-            );
-        }
+        let book = self.book(book)?;
+        let page = book
+            .page(*page_index)
+            .ok_or_else(|| Error::PageOutOfRange {
+                book: book.title.clone(),
+                page: *page_index,
+            })?;
+        // NOTE: the image directory is the one the book was parsed with, already normalized (see
+        // normalize_paths()), so whatever separators root_dir was given with do not matter here
 
         // NOTE: jpg files are 3 digits, so we need to zero-pad the index, any pages greater than 999 should have no problems
         // we also assume that exptension ".jpg" are always all lower-case
-        let absolute_image_path =
-            normalize_paths(format!("{}/{:03}.jpg", book.get_image_dir_paths(), index).as_str());
+        let absolute_image_path = normalize_paths(
+            format!("{}/{:03}.jpg", book.get_image_dir_paths(), page.index).as_str(),
+        );
         // verify if JPG actually exists
         if !std::path::Path::new(&absolute_image_path).exists() {
-            panic!("Image '{absolute_image_path}' not found");
+            return Err(Error::MissingImage(absolute_image_path.into()));
         }
         Ok(absolute_image_path)
     }
@@ -130,6 +352,7 @@ impl Manga109 {
     //      book (str): The title of the book to get the annotations of.
     //          The title must be contained in the list `self.books`.
     //      annotation_type (str) default `"annotations"` : The directory to load the xml data from.
+    //          (see AnnotationVersion, which is chosen once when constructing Manga109 instead)
     //      separate_by_tag (bool) default `True` : When set to `True`, each annotation data type
     //          ("frame", "face", "body", "text") will be stored in a different list in the output
     //          dictionary. When set to `False`, all of the annotation data will be stored in a
@@ -139,55 +362,29 @@ impl Manga109 {
     //  Returns:
     //      annotation (dict): The annotation data
     pub fn get_annotation(&self, book: &str, separate_by_tag: bool) -> Result<Annotation> {
-        let possible_book = self.books.iter().find(|b| b.title == book);
-        match possible_book {
-            Some(book) => {
-                let characters = book.characters.clone();
-                let mut pages = Vec::new();
-                for page in book.pages.iter() {
-                    let mut frames = Vec::new();
-                    for frame in page.frames.iter() {
-                        frames.push(frame.clone());
-                    }
-
-                    let mut texts = Vec::new();
-                    for text in page.texts.iter() {
-                        texts.push(text.clone());
-                    }
-
-                    let mut faces = Vec::new();
-                    for face in page.faces.iter() {
-                        faces.push(face.clone());
-                    }
-
-                    let mut bodies = Vec::new();
-                    for body in page.bodies.iter() {
-                        bodies.push(body.clone());
-                    }
-
-                    pages.push(Page {
-                        index: page.index.clone(),
-                        width: page.width.clone(),
-                        height: page.height.clone(),
-                        frames: frames,
-                        texts: texts,
-                        faces: faces,
-                        bodies: bodies,
-                    });
-                }
-                // if there are 0 pages, panic
-                if pages.is_empty() {
-                    panic!("No pages found in the book");
-                }
+        let book = self.book(book)?;
+        let characters = book.characters.clone();
+        if book.pages.is_empty() {
+            return Err(Error::NoPages(book.title.clone()));
+        }
 
-                Ok(Annotation {
-                    title: book.title.clone(),
-                    characters,
-                    pages,
+        let mut annotation = Annotation::new(book.title.clone());
+        annotation.characters = characters;
+        if separate_by_tag {
+            annotation.pages = book.pages.clone();
+        } else {
+            annotation.contents = book
+                .pages
+                .iter()
+                .map(|page| PageContents {
+                    index: page.index,
+                    width: page.width,
+                    height: page.height,
+                    contents: page.elements(),
                 })
-            }
-            None => Err(anyhow::anyhow!("Book not found")),
+                .collect();
         }
+        Ok(annotation)
     }
 }
 
@@ -200,6 +397,7 @@ impl Manga109 {
 //   but because they are flattened, for deserialization, I cannot structure it as a struct with those fields
 // TODO: add deserialization macros documented for quick_xml::de so that we an just deserialize directly
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Book {
     pub characters: Vec<Character>,
     pub pages: Vec<Page>,
@@ -208,10 +406,43 @@ pub struct Book {
     // Absolute paths to the annotation XML file and the root directory of images
     // they are usually in format of "{root_dir}/annotations/{book.title}.xml" and "{root_dir}/images"
     // NOTE: title is used as dir-paths, hence it is case sensitive!
+    // NOTE: these are machine specific, so they are not part of the serialized annotations
+    #[cfg_attr(feature = "serde", serde(skip))]
     annotation_filepaths: String, // Full paths with filename of the XML file (i.e. /foo/annotations/bar.xml)
-    image_root_dir: String,       // note that this is different from img_path()
+    #[cfg_attr(feature = "serde", serde(skip))]
+    image_root_dir: String, // note that this is different from img_path()
+
+    // Page::index -> position in `pages`, built when parsing; since `pages` is public, it is only
+    // a hint which page() verifies (and falls back to a scan when stale or missing)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    page_positions: HashMap<usize, usize>,
 }
+// Two books are equal when their annotations are: neither the (machine specific) paths nor the
+// page index take part, so a Book read back from JSON equals the one it was written from
+impl PartialEq for Book {
+    fn eq(&self, other: &Self) -> bool {
+        self.title == other.title
+            && self.characters == other.characters
+            && self.pages == other.pages
+    }
+}
+impl Eq for Book {}
 impl Book {
+    // The page annotated as <page index="N"> (the first one, should the index be repeated)
+    pub fn page(&self, index: usize) -> Option<&Page> {
+        self.page_positions
+            .get(&index)
+            .and_then(|&position| self.pages.get(position))
+            .filter(|page| page.index == index)
+            .or_else(|| self.pages.iter().find(|page| page.index == index))
+    }
+    fn index_pages(&mut self) {
+        self.page_positions.clear();
+        for (position, page) in self.pages.iter().enumerate() {
+            self.page_positions.entry(page.index).or_insert(position);
+        }
+    }
+
     pub fn get_image_dir_paths(&self) -> String {
         normalize_paths(self.image_root_dir.clone().as_str())
     }
@@ -221,12 +452,14 @@ impl Book {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Character {
     pub id: String,
     pub name: String,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Page {
     pub frames: Vec<Frame>,
     pub texts: Vec<Text>,
@@ -236,7 +469,27 @@ pub struct Page {
     pub width: u32,   // aka pixel_width
     pub height: u32,  // aka pixel_height
     //image_file_path: String,  // i.e. "/foo/images/bar/009.jpg" (index==9)
+
+    // Document order of the elements in the XML, as (tag, position in frames/texts/faces/bodies);
+    // the per-tag lists above cannot tell whether i.e. a <text> came before or after a <face>.
+    // Not part of the JSON/JSONL format: a deserialized page has none, so its elements() come in
+    // frame, text, face, body order (the cache keeps it though, see cache.rs)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    order: Vec<(AnnotationType, usize)>,
 }
+// The document order is not compared either, only the elements (per tag) and the page itself
+impl PartialEq for Page {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+            && self.width == other.width
+            && self.height == other.height
+            && self.frames == other.frames
+            && self.texts == other.texts
+            && self.faces == other.faces
+            && self.bodies == other.bodies
+    }
+}
+impl Eq for Page {}
 impl Clone for Page {
     fn clone(&self) -> Self {
         Page {
@@ -244,13 +497,84 @@ impl Clone for Page {
             texts: self.texts.clone(),
             faces: self.faces.clone(),
             bodies: self.bodies.clone(),
-            index: self.index.clone(),
-            width: self.width.clone(),
-            height: self.height.clone(),
+            index: self.index,
+            width: self.width,
+            height: self.height,
+            order: self.order.clone(),
         }
     }
 }
 impl Page {
+    pub fn new(index: usize, width: u32, height: u32) -> Self {
+        Page {
+            frames: Vec::new(),
+            texts: Vec::new(),
+            faces: Vec::new(),
+            bodies: Vec::new(),
+            index,
+            width,
+            height,
+            order: Vec::new(),
+        }
+    }
+
+    // Appends the element to its per-tag list, as well as to the end of the document order
+    pub fn push(&mut self, element: PageElement) {
+        let position = match element {
+            PageElement::Frame(frame) => {
+                self.frames.push(frame);
+                (AnnotationType::Frame, self.frames.len() - 1)
+            }
+            PageElement::Text(text) => {
+                self.texts.push(text);
+                (AnnotationType::Text, self.texts.len() - 1)
+            }
+            PageElement::Face(face) => {
+                self.faces.push(face);
+                (AnnotationType::Face, self.faces.len() - 1)
+            }
+            PageElement::Body(body) => {
+                self.bodies.push(body);
+                (AnnotationType::Body, self.bodies.len() - 1)
+            }
+        };
+        self.order.push(position);
+    }
+
+    // All the elements of the page in the same order as in the original XML file.
+    // Elements which were added to frames/texts/faces/bodies directly (rather than via push())
+    // are not part of the recorded order, so they come last, in frame, text, face, body order.
+    pub fn elements(&self) -> Vec<PageElement> {
+        let mut seen = [
+            vec![false; self.frames.len()],
+            vec![false; self.texts.len()],
+            vec![false; self.faces.len()],
+            vec![false; self.bodies.len()],
+        ];
+        let mut elements = Vec::new();
+        let all_positions = (0..self.frames.len())
+            .map(|i| (AnnotationType::Frame, i))
+            .chain((0..self.texts.len()).map(|i| (AnnotationType::Text, i)))
+            .chain((0..self.faces.len()).map(|i| (AnnotationType::Face, i)))
+            .chain((0..self.bodies.len()).map(|i| (AnnotationType::Body, i)));
+        for (kind, i) in self.order.iter().copied().chain(all_positions) {
+            let (slot, element) = match kind {
+                AnnotationType::Frame => (0, self.frames.get(i).cloned().map(PageElement::Frame)),
+                AnnotationType::Text => (1, self.texts.get(i).cloned().map(PageElement::Text)),
+                AnnotationType::Face => (2, self.faces.get(i).cloned().map(PageElement::Face)),
+                AnnotationType::Body => (3, self.bodies.get(i).cloned().map(PageElement::Body)),
+            };
+            // stale entries (i.e. the element was removed since) are dropped, duplicates skipped
+            if let Some(element) = element {
+                if !seen[slot][i] {
+                    seen[slot][i] = true;
+                    elements.push(element);
+                }
+            }
+        }
+        elements
+    }
+
     pub fn annotate(&self) -> Vec<Vec<String>> {
         let mut annotations = Vec::new();
         for frame in self.frames.clone() {
@@ -269,9 +593,48 @@ impl Page {
     }
 }
 
+// Any one of the elements of a page, see Page::elements()
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PageElement {
+    Frame(Frame),
+    Text(Text),
+    Face(Face),
+    Body(Body),
+}
+impl PageElement {
+    pub fn kind(&self) -> AnnotationType {
+        match self {
+            PageElement::Frame(_) => AnnotationType::Frame,
+            PageElement::Text(_) => AnnotationType::Text,
+            PageElement::Face(_) => AnnotationType::Face,
+            PageElement::Body(_) => AnnotationType::Body,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            PageElement::Frame(frame) => &frame.id,
+            PageElement::Text(text) => &text.id,
+            PageElement::Face(face) => &face.id,
+            PageElement::Body(body) => &body.id,
+        }
+    }
+
+    pub fn annotate(&self) -> Vec<String> {
+        match self {
+            PageElement::Frame(frame) => frame.annotate(),
+            PageElement::Text(text) => text.annotate(),
+            PageElement::Face(face) => face.annotate(),
+            PageElement::Body(body) => body.annotate(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Frame {
-    pub id: String, // it's actually an hexadecimal number HASH, but wei'll treate it as a &str
+    pub id: String, // it's actually an hexadecimal number HASH (see AnnotationId), but kept as written
     pub xmin: u32,
     pub ymin: u32,
     pub xmax: u32,
@@ -296,14 +659,15 @@ impl Frame {
     }
 
     pub fn get_width(&self) -> u32 {
-        self.xmax - self.xmin
+        self.bounding_box().width()
     }
     pub fn get_height(&self) -> u32 {
-        self.ymax - self.ymin
+        self.bounding_box().height()
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Text {
     pub id: String,
     pub xmin: u32,
@@ -333,14 +697,15 @@ impl Text {
     }
 
     pub fn get_width(&self) -> u32 {
-        self.xmax - self.xmin
+        self.bounding_box().width()
     }
     pub fn get_height(&self) -> u32 {
-        self.ymax - self.ymin
+        self.bounding_box().height()
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Face {
     pub id: String,
     pub xmin: u32,
@@ -370,14 +735,15 @@ impl Face {
     }
 
     pub fn get_width(&self) -> u32 {
-        self.xmax - self.xmin
+        self.bounding_box().width()
     }
     pub fn get_height(&self) -> u32 {
-        self.ymax - self.ymin
+        self.bounding_box().height()
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Body {
     pub id: String,
     pub xmin: u32,
@@ -407,291 +773,494 @@ impl Body {
     }
 
     pub fn get_width(&self) -> u32 {
-        self.xmax - self.xmin
+        self.bounding_box().width()
     }
     pub fn get_height(&self) -> u32 {
-        self.ymax - self.ymin
+        self.bounding_box().height()
     }
 }
 
-pub fn get_book(root_dir: &str, book_title: &str) -> Result<Book, String> {
-    println!(
-        "Parsing annotation file from '{}/annotations/{}.xml'",
-        root_dir, book_title
-    );
+pub fn get_book(root_dir: &str, book_title: &str) -> Result<Book> {
+    get_book_with_options(
+        root_dir,
+        book_title,
+        &AnnotationVersion::Latest,
+        &ParseOptions::default(),
+    )
+    .map(|(book, _)| book)
+}
 
+// Same as get_book(), but reads the XML of the given annotation version, and in lenient mode the
+// defects which were repaired or skipped are returned alongside the Book (in strict mode, the
+// diagnostics are always empty)
+pub fn get_book_with_options(
+    root_dir: &str,
+    book_title: &str,
+    annotation_version: &AnnotationVersion,
+    options: &ParseOptions,
+) -> Result<(Book, Vec<ParseDiagnostic>)> {
     let path = std::path::Path::new(root_dir);
-    // let's panic if root dir, annotations, or images are not found
+    // bail out if root dir, annotations, or images are not found
     if !path.exists() {
-        panic!("Root directory not found");
+        return Err(Error::MissingRootDir(path.to_path_buf()));
     }
-    let annotations_root_dir = path.join("annotations");
+    let annotations_root_dir = path.join(annotation_version.dir_name());
     if !annotations_root_dir.exists() {
-        panic!("Annotations directory not found");
+        return Err(Error::MissingAnnotationsDir(annotations_root_dir));
     }
     let images_root_dir = path.join("images");
     if !images_root_dir.exists() {
-        panic!("Images directory not found");
+        return Err(Error::MissingImagesDir(images_root_dir));
     }
 
     // see if BOOK exists
     let annotations_xml_file_paths = annotations_root_dir.join(format!("{}.xml", book_title));
     if !annotations_xml_file_paths.exists() {
-        panic!(
-            "Annotation XML file '{:?}' not found",
-            annotations_xml_file_paths
-        );
+        return Err(Error::MissingAnnotationFile(annotations_xml_file_paths));
     }
     let images_for_book_dir = images_root_dir.join(book_title);
     if !images_for_book_dir.exists() {
-        panic!(
-            "Images directory '{:?}' for book not found",
-            images_for_book_dir
-        );
+        return Err(Error::MissingBookImageDir(images_for_book_dir));
     }
 
-    parse_annotation_file_and_make_book(&annotations_xml_file_paths, &images_for_book_dir)
+    parse_annotation_file_and_make_book(&annotations_xml_file_paths, &images_for_book_dir, options)
 }
 
 fn parse_annotation_file_and_make_book(
     xml_paths: &std::path::Path,
     image_path: &std::path::Path,
-) -> Result<Book, String> {
-    // panic if the XML file is not found
+    options: &ParseOptions,
+) -> Result<(Book, Vec<ParseDiagnostic>)> {
     if !xml_paths.exists() {
-        panic!("XML file '{:?}' not found", xml_paths);
+        return Err(Error::MissingAnnotationFile(xml_paths.to_path_buf()));
     }
     if !image_path.exists() {
-        panic!("Images directory '{:?}' not found", image_path);
+        return Err(Error::MissingBookImageDir(image_path.to_path_buf()));
     }
-    let raw_xml = std::fs::read_to_string(xml_paths).map_err(|e| e.to_string())?;
-    parse_raw_xml_annotations_and_make_book(
+    let raw_xml = std::fs::read_to_string(xml_paths).map_err(|e| Error::Io {
+        path: xml_paths.to_path_buf(),
+        source: e,
+    })?;
+    parse_raw_xml_annotations_with_options(
         &raw_xml,
-        &normalize_paths(xml_paths.to_str().unwrap()).as_str(),
-        normalize_paths(image_path.to_str().unwrap()).as_str(),
+        normalize_paths(&xml_paths.to_string_lossy()).as_str(),
+        normalize_paths(&image_path.to_string_lossy()).as_str(),
+        options,
     )
 }
 
+// What the parser needs of an XML element, so that the same (strict/lenient) parsing works on both
+// a roxmltree::Node of the whole document and an element read by the streaming BookReader
+trait AnnotationNode {
+    fn tag(&self) -> &str;
+    fn attribute(&self, name: &str) -> Option<&str>;
+    fn text(&self) -> Option<&str>;
+}
+impl AnnotationNode for roxmltree::Node<'_, '_> {
+    fn tag(&self) -> &str {
+        self.tag_name().name()
+    }
+    fn attribute(&self, name: &str) -> Option<&str> {
+        roxmltree::Node::attribute(self, name)
+    }
+    fn text(&self) -> Option<&str> {
+        roxmltree::Node::text(self)
+    }
+}
+
+// Looks up a required attribute, `id` is only used for reporting which element was at fault
+fn attribute<'n>(
+    node: &'n impl AnnotationNode,
+    id: &Option<String>,
+    attribute: &str,
+) -> Result<&'n str> {
+    node.attribute(attribute)
+        .ok_or_else(|| Error::MissingAttribute {
+            element: node.tag().to_string(),
+            id: id.clone(),
+            attribute: attribute.to_string(),
+        })
+}
+
+// All numbers in the annotation XML are stored as quoted strings, so every one of them needs parsing
+fn parse_attribute<T: std::str::FromStr>(
+    node: &impl AnnotationNode,
+    id: &Option<String>,
+    attribute_name: &str,
+) -> Result<T> {
+    let value = attribute(node, id, attribute_name)?;
+    value.parse::<T>().map_err(|_| Error::InvalidAttribute {
+        element: node.tag().to_string(),
+        id: id.clone(),
+        attribute: attribute_name.to_string(),
+        value: value.to_string(),
+    })
+}
+
+// id, xmin, ymin, xmax, ymax are common to frame, text, face and body
+// (id, xmin, ymin, xmax, ymax)
+type IdAndRect = (String, u32, u32, u32, u32);
+
+fn parse_id_and_rect(node: &impl AnnotationNode) -> Result<IdAndRect> {
+    let id = attribute(node, &None, "id")?.to_string();
+    let some_id = Some(id.clone());
+    let xmin = parse_attribute::<u32>(node, &some_id, "xmin")?;
+    let ymin = parse_attribute::<u32>(node, &some_id, "ymin")?;
+    let xmax = parse_attribute::<u32>(node, &some_id, "xmax")?;
+    let ymax = parse_attribute::<u32>(node, &some_id, "ymax")?;
+    Ok((id, xmin, ymin, xmax, ymax))
+}
+
+// Book-wide state while parsing; decides (based on ParseOptions) whether a defect is fatal or
+// whether it gets recorded as a diagnostic so the caller can repair/skip the element
+struct ParseContext {
+    options: ParseOptions,
+    title: String,
+    page: Option<usize>,
+    diagnostics: Vec<ParseDiagnostic>,
+}
+impl ParseContext {
+    fn record(&mut self, element: &str, id: Option<String>, defect: Defect, action: ParseAction) {
+        self.diagnostics.push(ParseDiagnostic {
+            title: self.title.clone(),
+            page: self.page,
+            element: element.to_string(),
+            id,
+            defect,
+            action,
+        });
+    }
+
+    // Strict: passes the error through.  Lenient: records the element as skipped and returns None
+    fn skip_on_error<T>(&mut self, result: Result<T>) -> Result<Option<T>> {
+        let error = match result {
+            Ok(value) => return Ok(Some(value)),
+            Err(error) if self.options.strict => return Err(error),
+            Err(error) => error,
+        };
+        match error {
+            Error::MissingAttribute {
+                element,
+                id,
+                attribute,
+            } => self.record(
+                &element,
+                id,
+                Defect::MissingAttribute(attribute),
+                ParseAction::Skipped,
+            ),
+            Error::InvalidAttribute {
+                element,
+                id,
+                attribute,
+                value,
+            } => self.record(
+                &element,
+                id,
+                Defect::InvalidAttribute { attribute, value },
+                ParseAction::Skipped,
+            ),
+            // anything else is not an element defect, so it's fatal regardless
+            error => return Err(error),
+        }
+        Ok(None)
+    }
+
+    // Strict: the box is kept as written (as it always has been, the annotations have a few boxes
+    // which stick out of the page by a pixel or so).  Lenient: inverted boxes are swapped around and
+    // boxes which stick out of the page are clamped to it, a box which ends up with no area after
+    // being clamped is skipped
+    fn check_rect(
+        &mut self,
+        element: &str,
+        id: &str,
+        (xmin, ymin, xmax, ymax): (u32, u32, u32, u32),
+        page_width: u32,
+        page_height: u32,
+    ) -> Option<(u32, u32, u32, u32)> {
+        let (mut xmin, mut ymin, mut xmax, mut ymax) = (xmin, ymin, xmax, ymax);
+        if self.options.strict {
+            return Some((xmin, ymin, xmax, ymax));
+        }
+        if xmin > xmax || ymin > ymax {
+            self.record(
+                element,
+                Some(id.to_string()),
+                Defect::InvertedBox,
+                ParseAction::Repaired,
+            );
+            (xmin, xmax) = (xmin.min(xmax), xmin.max(xmax));
+            (ymin, ymax) = (ymin.min(ymax), ymin.max(ymax));
+        }
+        if xmax > page_width || ymax > page_height {
+            (xmin, xmax) = (xmin.min(page_width), xmax.min(page_width));
+            (ymin, ymax) = (ymin.min(page_height), ymax.min(page_height));
+            let action = if xmin == xmax || ymin == ymax {
+                ParseAction::Skipped
+            } else {
+                ParseAction::Repaired
+            };
+            self.record(element, Some(id.to_string()), Defect::OutsidePage, action);
+            if action == ParseAction::Skipped {
+                return None;
+            }
+        }
+        Some((xmin, ymin, xmax, ymax))
+    }
+}
+
+// Each of these returns None when (in lenient mode) the element had to be skipped
+impl ParseContext {
+    fn parse_character(&mut self, node: &impl AnnotationNode) -> Result<Option<Character>> {
+        let parsed = attribute(node, &None, "id").and_then(|id| {
+            let name = attribute(node, &Some(id.to_string()), "name")?;
+            Ok(Character {
+                id: id.to_string(),
+                name: name.to_string(),
+            })
+        });
+        self.skip_on_error(parsed)
+    }
+
+    // (index, width, height) of a <page>; also makes it the page the diagnostics are reported on
+    fn parse_page(&mut self, node: &impl AnnotationNode) -> Result<Option<(usize, u32, u32)>> {
+        self.page = None;
+        let parsed = parse_attribute::<usize>(node, &None, "index").and_then(|index| {
+            let width = parse_attribute::<u32>(node, &None, "width")?;
+            let height = parse_attribute::<u32>(node, &None, "height")?;
+            Ok((index, width, height))
+        });
+        let parsed = self.skip_on_error(parsed)?;
+        self.page = parsed.map(|(index, _, _)| index);
+        Ok(parsed)
+    }
+
+    // Any element of a page, None as well for the elements which are not annotations
+    fn parse_element(
+        &mut self,
+        node: &impl AnnotationNode,
+        width: u32,
+        height: u32,
+    ) -> Result<Option<PageElement>> {
+        Ok(match node.tag() {
+            "frame" => self
+                .parse_frame(node, width, height)?
+                .map(PageElement::Frame),
+            "text" => self.parse_text(node, width, height)?.map(PageElement::Text),
+            "face" => self.parse_face(node, width, height)?.map(PageElement::Face),
+            "body" => self.parse_body(node, width, height)?.map(PageElement::Body),
+            _ => None,
+        })
+    }
+
+    fn parse_frame(
+        &mut self,
+        node: &impl AnnotationNode,
+        width: u32,
+        height: u32,
+    ) -> Result<Option<Frame>> {
+        let Some((id, xmin, ymin, xmax, ymax)) = self.skip_on_error(parse_id_and_rect(node))?
+        else {
+            return Ok(None);
+        };
+        let Some((xmin, ymin, xmax, ymax)) =
+            self.check_rect("frame", &id, (xmin, ymin, xmax, ymax), width, height)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(Frame {
+            id,
+            xmin,
+            ymin,
+            xmax,
+            ymax,
+        }))
+    }
+
+    fn parse_text(
+        &mut self,
+        node: &impl AnnotationNode,
+        width: u32,
+        height: u32,
+    ) -> Result<Option<Text>> {
+        let Some((id, xmin, ymin, xmax, ymax)) = self.skip_on_error(parse_id_and_rect(node))?
+        else {
+            return Ok(None);
+        };
+        let Some((xmin, ymin, xmax, ymax)) =
+            self.check_rect("text", &id, (xmin, ymin, xmax, ymax), width, height)
+        else {
+            return Ok(None);
+        };
+        let value = match node.text() {
+            Some(value) => value.to_string(),
+            None if self.options.strict => return Err(Error::EmptyText { id }),
+            None => {
+                // the box itself is still good (i.e. for text detection), so keep it as ""
+                self.record(
+                    "text",
+                    Some(id.clone()),
+                    Defect::EmptyText,
+                    ParseAction::Repaired,
+                );
+                String::new()
+            }
+        };
+        Ok(Some(Text {
+            id,
+            xmin,
+            ymin,
+            xmax,
+            ymax,
+            value,
+        }))
+    }
+
+    // face and body are identical apart from their tag
+    fn parse_character_rect(
+        &mut self,
+        node: &impl AnnotationNode,
+        width: u32,
+        height: u32,
+    ) -> Result<Option<(IdAndRect, String)>> {
+        let parsed = parse_id_and_rect(node).and_then(|rect| {
+            let character = attribute(node, &Some(rect.0.clone()), "character")?;
+            Ok((rect, character.to_string()))
+        });
+        let Some(((id, xmin, ymin, xmax, ymax), character)) = self.skip_on_error(parsed)? else {
+            return Ok(None);
+        };
+        let tag = node.tag();
+        let Some((xmin, ymin, xmax, ymax)) =
+            self.check_rect(tag, &id, (xmin, ymin, xmax, ymax), width, height)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(((id, xmin, ymin, xmax, ymax), character)))
+    }
+
+    fn parse_face(
+        &mut self,
+        node: &impl AnnotationNode,
+        width: u32,
+        height: u32,
+    ) -> Result<Option<Face>> {
+        Ok(self.parse_character_rect(node, width, height)?.map(
+            |((id, xmin, ymin, xmax, ymax), character)| Face {
+                id,
+                xmin,
+                ymin,
+                xmax,
+                ymax,
+                character,
+            },
+        ))
+    }
+
+    fn parse_body(
+        &mut self,
+        node: &impl AnnotationNode,
+        width: u32,
+        height: u32,
+    ) -> Result<Option<Body>> {
+        Ok(self.parse_character_rect(node, width, height)?.map(
+            |((id, xmin, ymin, xmax, ymax), character)| Body {
+                id,
+                xmin,
+                ymin,
+                xmax,
+                ymax,
+                character,
+            },
+        ))
+    }
+}
+
 // Takes in RAW XML string, makes it easier to unit-test witthout reading from a file directly
+#[cfg(test)]
 fn parse_raw_xml_annotations_and_make_book(
     raw_xml: &str,
     xml_pathsname: &str,
     image_pathsname: &str,
-) -> Result<Book, String> {
+) -> Result<Book> {
+    parse_raw_xml_annotations_with_options(
+        raw_xml,
+        xml_pathsname,
+        image_pathsname,
+        &ParseOptions::default(),
+    )
+    .map(|(book, _)| book)
+}
+
+fn parse_raw_xml_annotations_with_options(
+    raw_xml: &str,
+    xml_pathsname: &str,
+    image_pathsname: &str,
+    options: &ParseOptions,
+) -> Result<(Book, Vec<ParseDiagnostic>)> {
     // because the format is very trivial, for now, I'll just use roxmltree
-    let xml = roxmltree::Document::parse(raw_xml).map_err(|e| e.to_string())?;
+    let xml = roxmltree::Document::parse(raw_xml).map_err(|e| {
+        let pos = e.pos();
+        Error::Xml {
+            path: xml_pathsname.to_string(),
+            line: pos.row,
+            column: pos.col,
+            message: e.to_string(),
+        }
+    })?;
     let root = xml.root_element();
 
-    let title = root.attribute("title").ok_or("title not found")?;
+    // without a title, there is no book to speak of, so this one is always fatal
+    let title = attribute(&root, &None, "title")?;
     let mut book = Book {
         characters: Vec::new(),
         pages: Vec::new(),
         title: title.to_string(),
         annotation_filepaths: xml_pathsname.to_string(),
         image_root_dir: image_pathsname.to_string(),
+        page_positions: HashMap::new(),
+    };
+    let mut ctx = ParseContext {
+        options: *options,
+        title: title.to_string(),
+        page: None,
+        diagnostics: Vec::new(),
     };
 
     let mut characters = Vec::new();
     for character in root.descendants().filter(|n| n.has_tag_name("character")) {
-        let id = character.attribute("id").unwrap().to_string();
-        let name = character.attribute("name").unwrap().to_string();
-        characters.push(Character { id: id, name: name });
+        if let Some(character) = ctx.parse_character(&character)? {
+            characters.push(character);
+        }
     }
     book.characters = characters;
 
     let mut pages = Vec::new();
     for page in root.descendants().filter(|n| n.has_tag_name("page")) {
-        let index = page
-            .attribute("index")
-            .unwrap()
-            .to_string()
-            .parse::<usize>()
-            .expect("Pages: Expected usize for index");
-        let width = page
-            .attribute("width")
-            .unwrap()
-            .to_string()
-            .parse::<u32>()
-            .expect("Pages: Expected u32 for width");
-        let height = page
-            .attribute("height")
-            .unwrap()
-            .to_string()
-            .parse::<u32>()
-            .expect("Pages: Expected u32 for height");
-
-        let mut frames = Vec::new();
-        for frame in page.descendants().filter(|n| n.has_tag_name("frame")) {
-            let id = frame.attribute("id").unwrap().to_string();
-            let xmin = frame
-                .attribute("xmin")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Frames: Expected u32 for id={id} for xmin");
-            let ymin = frame
-                .attribute("ymin")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Frames: Expected u32 for id={id} for ymin");
-            let xmax = frame
-                .attribute("xmax")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Frames: Expected u32 for id={id} for xmax");
-            let ymax = frame
-                .attribute("ymax")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Frames: Expected u32 for id={id} for ymax");
-            frames.push(Frame {
-                id: id,
-                xmin: xmin,
-                ymin: ymin,
-                xmax: xmax,
-                ymax: ymax,
-            });
-        }
-
-        let mut texts = Vec::new();
-        for text in page.descendants().filter(|n| n.has_tag_name("text")) {
-            let id = text.attribute("id").unwrap().to_string();
-            let xmin = text
-                .attribute("xmin")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Texts: Expected u32 for id={id} for xmin");
-            let ymin = text
-                .attribute("ymin")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Texts: Expected u32 for id={id} for ymin");
-            let xmax = text
-                .attribute("xmax")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Texts: Expected u32 for id={id} for xmax");
-            let ymax = text
-                .attribute("ymax")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Texts: Expected u32 for id={id} for ymax");
-            let value = text.text().unwrap().to_string();
-            texts.push(Text {
-                id: id,
-                xmin: xmin,
-                ymin: ymin,
-                xmax: xmax,
-                ymax: ymax,
-                value: value,
-            });
-        }
-
-        let mut faces = Vec::new();
-        for face in page.descendants().filter(|n| n.has_tag_name("face")) {
-            let id = face.attribute("id").unwrap().to_string();
-            let xmin = face
-                .attribute("xmin")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Texts: Expected u32 for id={id} for xmin");
-            let ymin = face
-                .attribute("ymin")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Texts: Expected u32 for id={id} for ymin");
-            let xmax = face
-                .attribute("xmax")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Texts: Expected u32 for id={id} for xmax");
-            let ymax = face
-                .attribute("ymax")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Texts: Expected u32 for id={id} for ymax");
-            let character = face.attribute("character").unwrap().to_string();
-            faces.push(Face {
-                id: id,
-                xmin: xmin,
-                ymin: ymin,
-                xmax: xmax,
-                ymax: ymax,
-                character: character,
-            });
-        }
-
-        let mut bodies = Vec::new();
-        for body in page.descendants().filter(|n| n.has_tag_name("body")) {
-            let id = body.attribute("id").unwrap().to_string();
-            let xmin = body
-                .attribute("xmin")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Bodies: Expected u32 for id={id} for xmin");
-            let ymin = body
-                .attribute("ymin")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Bodies: Expected u32 for id={id} for ymin");
-            let xmax = body
-                .attribute("xmax")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Bodies: Expected u32 for id={id} for xmax");
-            let ymax = body
-                .attribute("ymax")
-                .unwrap()
-                .to_string()
-                .parse::<u32>()
-                .expect("Bodies: Expected u32 for id={id} for ymax");
-            let character = body.attribute("character").unwrap().to_string();
-            bodies.push(Body {
-                id: id,
-                xmin: xmin,
-                ymin: ymin,
-                xmax: xmax,
-                ymax: ymax,
-                character: character,
-            });
-        }
-
-        pages.push(Page {
-            index: index,
-            width: width,
-            height: height,
-            frames: frames,
-            texts: texts,
-            faces: faces,
-            bodies: bodies,
-        });
+        let Some((index, width, height)) = ctx.parse_page(&page)? else {
+            continue;
+        };
+
+        let mut parsed_page = Page::new(index, width, height);
+        // single pass over the page so that the document order of the (mixed) elements is kept
+        for node in page.descendants().filter(|n| n.is_element()) {
+            if let Some(element) = ctx.parse_element(&node, width, height)? {
+                parsed_page.push(element);
+            }
+        }
+        pages.push(parsed_page);
     }
     book.pages = pages;
+    book.index_pages();
 
-    Ok(book)
+    Ok((book, ctx.diagnostics))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parse_annotation() {
-        let xml = r#"
+    const TEST_ANNOTATION_XML: &str = r#"
             <annotation title="title">
                 <characters>
                     <character id="id1" name="name1"/>
@@ -714,6 +1283,10 @@ mod tests {
             </annotation>
         "#;
 
+    #[test]
+    fn test_parse_annotation() {
+        let xml = TEST_ANNOTATION_XML;
+
         let book = parse_raw_xml_annotations_and_make_book(xml, "", "").unwrap();
         assert_eq!(book.title, ("title".to_string()));
 
@@ -805,4 +1378,457 @@ mod tests {
         assert_eq!(bodies[0].ymax, 50);
         assert_eq!(bodies[0].character, "character2".to_string());
     }
+
+    #[test]
+    fn test_xml_round_trip() {
+        let book = parse_raw_xml_annotations_and_make_book(TEST_ANNOTATION_XML, "", "").unwrap();
+        let xml = book.to_xml_string();
+        let reparsed = parse_raw_xml_annotations_and_make_book(&xml, "", "").unwrap();
+        assert_eq!(reparsed, book);
+        assert_eq!(reparsed.to_xml_string(), xml);
+
+        // hex ids keep their zero-padding and values with markup/line breaks survive as-is
+        let xml = r#"<book title="A &amp; B"><pages><page index="3" width="100" height="200">
+            <text id="0000000b" xmin="10" ymin="20" xmax="30" ymax="40">&lt;え&gt;
+ほんと？</text>
+        </page></pages></book>"#;
+        let book = parse_raw_xml_annotations_and_make_book(xml, "", "").unwrap();
+        let reparsed =
+            parse_raw_xml_annotations_and_make_book(&book.to_xml_string(), "", "").unwrap();
+        assert_eq!(reparsed, book);
+        assert_eq!(reparsed.title, "A & B");
+        assert_eq!(reparsed.pages[0].texts[0].id, "0000000b");
+        assert_eq!(reparsed.pages[0].texts[0].value, "<え>\nほんと？");
+    }
+
+    #[test]
+    fn test_parse_annotation_errors() {
+        let xml = r#"<book title="title"><pages><page index="1" width="100" height="200">
+            <frame id="0000000a" xmin="10" ymin="twenty" xmax="30" ymax="40"/>
+        </page></pages></book>"#;
+        match parse_raw_xml_annotations_and_make_book(xml, "", "") {
+            Err(Error::InvalidAttribute {
+                element,
+                id,
+                attribute,
+                value,
+            }) => {
+                assert_eq!(element, "frame");
+                assert_eq!(id, Some("0000000a".to_string()));
+                assert_eq!(attribute, "ymin");
+                assert_eq!(value, "twenty");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let xml = r#"<book title="title"><pages><page index="1" height="200"/></pages></book>"#;
+        match parse_raw_xml_annotations_and_make_book(xml, "", "") {
+            Err(Error::MissingAttribute {
+                element, attribute, ..
+            }) => {
+                assert_eq!(element, "page");
+                assert_eq!(attribute, "width");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let xml = "<book title=\"title\">\n<pages>\n</book>";
+        match parse_raw_xml_annotations_and_make_book(xml, "bad.xml", "") {
+            Err(Error::Xml {
+                path, line, column, ..
+            }) => {
+                assert_eq!(path, "bad.xml");
+                assert_eq!(line, 3);
+                assert!(column > 0);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_annotation_lenient() {
+        let xml = r#"
+            <book title="title">
+                <characters>
+                    <character id="00000001"/>
+                    <character id="00000002" name="name2"/>
+                </characters>
+                <pages>
+                    <page index="0" width="100" height="200">
+                        <frame id="00000003" xmin="30" ymin="40" xmax="10" ymax="20"/>
+                        <text id="00000004" xmin="10" ymin="20" xmax="30" ymax="40"/>
+                        <text id="00000005" xmin="90" ymin="20" xmax="120" ymax="40">value</text>
+                        <face id="00000006" xmin="150" ymin="20" xmax="160" ymax="40" character="00000002"/>
+                        <body id="00000007" xmin="x" ymin="20" xmax="30" ymax="40" character="00000002"/>
+                    </page>
+                    <page width="100" height="200"/>
+                </pages>
+            </book>
+        "#;
+
+        // strict stops at the first defect
+        assert!(parse_raw_xml_annotations_and_make_book(xml, "", "").is_err());
+        // but keeps the boxes as they are written, as it always has
+        let boxes_only = r#"<book title="title"><pages>
+                <page index="0" width="100" height="200">
+                    <frame id="00000003" xmin="30" ymin="40" xmax="10" ymax="20"/>
+                    <text id="00000005" xmin="90" ymin="20" xmax="120" ymax="40">value</text>
+                </page>
+            </pages></book>"#;
+        let (book, diagnostics) =
+            parse_raw_xml_annotations_with_options(boxes_only, "", "", &ParseOptions::default())
+                .unwrap();
+        assert!(diagnostics.is_empty());
+        assert_eq!(book.pages[0].frames[0].xmin, 30);
+        assert_eq!(book.pages[0].texts[0].xmax, 120);
+
+        let (book, diagnostics) =
+            parse_raw_xml_annotations_with_options(xml, "", "", &ParseOptions::lenient()).unwrap();
+        assert_eq!(book.characters.len(), 1);
+        assert_eq!(book.pages.len(), 1);
+        let page = &book.pages[0];
+        assert_eq!(
+            (
+                page.frames[0].xmin,
+                page.frames[0].ymin,
+                page.frames[0].xmax,
+                page.frames[0].ymax
+            ),
+            (10, 20, 30, 40)
+        );
+        assert_eq!(page.texts.len(), 2);
+        assert_eq!(page.texts[0].value, "");
+        assert_eq!(page.texts[1].xmax, 100);
+        assert!(page.faces.is_empty());
+        assert!(page.bodies.is_empty());
+
+        let summary: Vec<_> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.page,
+                    d.element.as_str(),
+                    d.id.as_deref(),
+                    &d.defect,
+                    d.action,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    None,
+                    "character",
+                    Some("00000001"),
+                    &Defect::MissingAttribute("name".to_string()),
+                    ParseAction::Skipped
+                ),
+                (
+                    Some(0),
+                    "frame",
+                    Some("00000003"),
+                    &Defect::InvertedBox,
+                    ParseAction::Repaired
+                ),
+                (
+                    Some(0),
+                    "text",
+                    Some("00000004"),
+                    &Defect::EmptyText,
+                    ParseAction::Repaired
+                ),
+                (
+                    Some(0),
+                    "text",
+                    Some("00000005"),
+                    &Defect::OutsidePage,
+                    ParseAction::Repaired
+                ),
+                (
+                    Some(0),
+                    "face",
+                    Some("00000006"),
+                    &Defect::OutsidePage,
+                    ParseAction::Skipped
+                ),
+                (
+                    Some(0),
+                    "body",
+                    Some("00000007"),
+                    &Defect::InvalidAttribute {
+                        attribute: "xmin".to_string(),
+                        value: "x".to_string()
+                    },
+                    ParseAction::Skipped
+                ),
+                (
+                    None,
+                    "page",
+                    None,
+                    &Defect::MissingAttribute("index".to_string()),
+                    ParseAction::Skipped
+                ),
+            ]
+        );
+    }
+
+    // Lays out a minimal dataset root (books.txt, annotations/{title}.xml, images/{title}/) under
+    // the system temp dir; `name` keeps tests running in parallel from stepping on each other
+    fn make_test_dataset(name: &str, books: &[(&str, &str)]) -> std::path::PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("manga109api_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("annotations")).unwrap();
+        let mut books_txt = String::new();
+        for (title, xml) in books {
+            books_txt.push_str(&format!("{}\n", title));
+            std::fs::create_dir_all(root.join("images").join(title)).unwrap();
+            std::fs::write(root.join("annotations").join(format!("{}.xml", title)), xml).unwrap();
+        }
+        std::fs::write(root.join("books.txt"), books_txt).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_lazy_loading() {
+        let root = make_test_dataset(
+            "lazy",
+            &[
+                ("Good", TEST_ANNOTATION_XML),
+                ("Broken", "<book title=\"Broken\"><pages>"),
+            ],
+        );
+        let manga109 = Manga109::new(root.to_str().unwrap()).unwrap();
+        assert_eq!(
+            manga109.titles(),
+            ["Good".to_string(), "Broken".to_string()]
+        );
+        assert_eq!(manga109.loaded_books().count(), 0);
+
+        let book = manga109.book("Good").unwrap();
+        assert_eq!(book.pages.len(), 2);
+        // cached, so the very same Book comes back
+        assert!(std::ptr::eq(book, manga109.book("Good").unwrap()));
+        assert_eq!(manga109.loaded_books().count(), 1);
+
+        assert!(matches!(manga109.book("Broken"), Err(Error::Xml { .. })));
+        // the streaming reader gives the same, and does not load the book
+        let (streamed, _) = manga109.book_reader("Good").unwrap().read_book().unwrap();
+        assert_eq!(&streamed, manga109.book("Good").unwrap());
+        assert!(matches!(
+            manga109
+                .book_reader("Broken")
+                .and_then(|reader| reader.read_book()),
+            Err(Error::Xml { .. })
+        ));
+        assert!(matches!(
+            manga109.book("Missing"),
+            Err(Error::MissingBook(_))
+        ));
+        assert!(manga109.load_all().is_err());
+
+        // a title listed in books.txt without its XML is caught up-front
+        std::fs::remove_file(root.join("annotations").join("Broken.xml")).unwrap();
+        assert!(matches!(
+            Manga109::new(root.to_str().unwrap()),
+            Err(Error::MissingAnnotationFile(_))
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_page_elements_in_document_order() {
+        let xml = r#"<book title="Mixed"><pages>
+            <page index="0" width="100" height="200">
+                <text id="00000001" xmin="10" ymin="20" xmax="30" ymax="40">first</text>
+                <frame id="00000002" xmin="0" ymin="0" xmax="50" ymax="50"/>
+                <face id="00000003" xmin="10" ymin="20" xmax="30" ymax="40" character="0000000a"/>
+                <text id="00000004" xmin="10" ymin="20" xmax="30" ymax="40">second</text>
+            </page>
+        </pages></book>"#;
+        let book = parse_raw_xml_annotations_and_make_book(xml, "", "").unwrap();
+        let ids: Vec<_> = book.pages[0]
+            .elements()
+            .iter()
+            .map(|e| (e.kind(), e.id().to_string()))
+            .collect();
+        let expected = vec![
+            (AnnotationType::Text, "00000001".to_string()),
+            (AnnotationType::Frame, "00000002".to_string()),
+            (AnnotationType::Face, "00000003".to_string()),
+            (AnnotationType::Text, "00000004".to_string()),
+        ];
+        assert_eq!(ids, expected);
+
+        // elements added directly to the per-tag lists still show up, after the recorded ones
+        let mut page = book.pages[0].clone();
+        let mut extra = page.frames[0].clone();
+        extra.id = "00000005".to_string();
+        page.frames.push(extra);
+        assert_eq!(page.elements().last().unwrap().id(), "00000005");
+        assert_eq!(page.elements().len(), 5);
+
+        let root = make_test_dataset("elements", &[("Mixed", xml)]);
+        let manga109 = Manga109::new(root.to_str().unwrap()).unwrap();
+        let annotation = manga109.get_annotation("Mixed", false).unwrap();
+        assert!(annotation.pages.is_empty());
+        let contents = &annotation.contents[0];
+        assert_eq!(
+            (contents.index, contents.width, contents.height),
+            (0, 100, 200)
+        );
+        assert_eq!(contents.contents, book.pages[0].elements());
+        let annotation = manga109.get_annotation("Mixed", true).unwrap();
+        assert!(annotation.contents.is_empty());
+        assert_eq!(annotation.pages, book.pages);
+
+        // writing the book back keeps the original order too
+        let reparsed =
+            parse_raw_xml_annotations_and_make_book(&book.to_xml_string(), "", "").unwrap();
+        assert_eq!(reparsed.pages[0].elements(), book.pages[0].elements());
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_annotation_version() {
+        assert_eq!(
+            "v2018".parse::<AnnotationVersion>().unwrap(),
+            AnnotationVersion::V2018
+        );
+        assert_eq!(
+            "annotations.v2020.12.18"
+                .parse::<AnnotationVersion>()
+                .unwrap(),
+            AnnotationVersion::V2020
+        );
+        assert_eq!(
+            "latest".parse::<AnnotationVersion>().unwrap(),
+            AnnotationVersion::Latest
+        );
+        assert_eq!(
+            "annotations.fixed".parse::<AnnotationVersion>().unwrap(),
+            AnnotationVersion::Custom("annotations.fixed".to_string())
+        );
+
+        let root = make_test_dataset("version", &[("Good", TEST_ANNOTATION_XML)]);
+        let root_dir = root.to_str().unwrap();
+        let v2018_dir = root.join(AnnotationVersion::V2018.dir_name());
+        std::fs::create_dir_all(&v2018_dir).unwrap();
+        std::fs::write(
+            v2018_dir.join("Good.xml"),
+            r#"<book title="Good"><pages><page index="0" width="10" height="10"/></pages></book>"#,
+        )
+        .unwrap();
+
+        let latest = Manga109::new(root_dir).unwrap();
+        assert_eq!(latest.book("Good").unwrap().pages.len(), 2);
+        let v2018 = Manga109::new_with_version(
+            root_dir,
+            &AnnotationVersion::V2018,
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(v2018.book("Good").unwrap().pages.len(), 1);
+        assert!(matches!(
+            Manga109::new_with_version(
+                root_dir,
+                &AnnotationVersion::V2020,
+                &ParseOptions::default()
+            ),
+            Err(Error::MissingAnnotationsDir(_))
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_img_path_by_page_index() {
+        // page 1 is skipped and pages 3/2 are out of order, so the positions in Book::pages no
+        // longer match the page indices (nor the JPEG names)
+        let xml = r#"<book title="Gaps"><pages>
+                <page index="0" width="100" height="200"/>
+                <page index="3" width="100" height="200"/>
+                <page index="2" width="100" height="200"/>
+            </pages></book>"#;
+        let root = make_test_dataset("img_path", &[("Gaps", xml)]);
+        for jpg in ["000.jpg", "002.jpg"] {
+            std::fs::write(root.join("images/Gaps").join(jpg), "").unwrap();
+        }
+        let manga109 = Manga109::new(root.to_str().unwrap()).unwrap();
+        let book = manga109.book("Gaps").unwrap();
+        assert_eq!(book.page(3).unwrap().index, 3);
+        assert!(book.page(1).is_none());
+
+        assert!(manga109
+            .img_path("Gaps", &0)
+            .unwrap()
+            .ends_with("/Gaps/000.jpg"));
+        assert!(manga109
+            .img_path("Gaps", &2)
+            .unwrap()
+            .ends_with("/Gaps/002.jpg"));
+        // in the XML, but not on disk
+        assert!(matches!(
+            manga109.img_path("Gaps", &3),
+            Err(Error::MissingImage(path)) if path.ends_with("Gaps/003.jpg")
+        ));
+        // neither
+        assert!(matches!(
+            manga109.img_path("Gaps", &1),
+            Err(Error::PageOutOfRange { page: 1, .. })
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_missing_dataset() {
+        match Manga109::new("/nonexistent/manga109") {
+            Err(Error::MissingRootDir(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match get_book("/nonexistent/manga109", "ARMS") {
+            Err(Error::MissingRootDir(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_load_page_image() {
+        let xml = r#"<book title="Good"><pages>
+                <page index="0" width="100" height="200">
+                    <text id="00000001" xmin="10" ymin="20" xmax="30" ymax="40">value1</text>
+                </page>
+            </pages></book>"#;
+        let root = make_test_dataset("page_image", &[("Good", xml)]);
+        let manga109 = Manga109::new(root.to_str().unwrap()).unwrap();
+        let page = &manga109.book("Good").unwrap().pages[0];
+        // not on disk yet
+        assert!(matches!(
+            manga109.load_page_image("Good", page.index),
+            Err(Error::MissingImage(_))
+        ));
+        let path = root.join("images").join("Good").join("000.jpg");
+        image::DynamicImage::new_rgb8(page.width, page.height)
+            .save(&path)
+            .unwrap();
+
+        let image = manga109.load_page_image("Good", page.index).unwrap();
+        assert_eq!((image.width(), image.height()), (100, 200));
+        let crop = image.crop(&page.texts[0], 15); // the left edge stops at x=0
+        assert_eq!((crop.width(), crop.height()), (45, 50));
+        // served from the cache, even once the file is gone
+        std::fs::remove_file(&path).unwrap();
+        assert!(manga109.load_page_image("Good", page.index).is_ok());
+        manga109.set_image_cache_capacity(0);
+        assert!(manga109.load_page_image("Good", page.index).is_err());
+
+        // a file which is not an image
+        std::fs::write(&path, "not a jpeg").unwrap();
+        assert!(matches!(
+            manga109.load_page_image("Good", page.index),
+            Err(Error::ImageDecode { .. })
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use image::DynamicImage;
+
+use crate::{Error, HasBoundingBox, Manga109, Result};
+
+// how many decoded pages Manga109 keeps around by default; a decoded page is width * height * 3
+// bytes (~5MB for a 1654x1170 spread), and the typical access pattern is all the boxes of one page
+// after the other, so a handful is plenty
+pub const DEFAULT_IMAGE_CACHE_CAPACITY: usize = 8;
+
+// A decoded page image, shared with the cache (cheap to clone)
+#[derive(Debug, Clone)]
+pub struct PageImage {
+    image: Arc<DynamicImage>,
+}
+
+impl PageImage {
+    pub fn image(&self) -> &DynamicImage {
+        &self.image
+    }
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    // Crop the bounding box of a frame/text/face/body (or a plain BoundingBox) out of the page,
+    // grown by `padding` pixels on each side and clamped to the image; a box entirely off the
+    // image gives an empty (0x0) image
+    pub fn crop(&self, element: &impl HasBoundingBox, padding: u32) -> DynamicImage {
+        let bbox = element
+            .bounding_box()
+            .pad(padding)
+            .clamp(self.width(), self.height());
+        self.image
+            .crop_imm(bbox.xmin, bbox.ymin, bbox.width(), bbox.height())
+    }
+}
+
+// Least recently used decoded pages, keyed by (title, Page::index); the most recently used is at
+// the back
+#[derive(Debug)]
+pub(crate) struct ImageCache {
+    capacity: usize,
+    entries: VecDeque<((String, usize), Arc<DynamicImage>)>,
+}
+
+impl ImageCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        ImageCache {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, title: &str, page: usize) -> Option<Arc<DynamicImage>> {
+        let position = self
+            .entries
+            .iter()
+            .position(|((t, p), _)| t == title && *p == page)?;
+        let entry = self.entries.remove(position)?;
+        let image = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(image)
+    }
+
+    fn insert(&mut self, title: &str, page: usize, image: Arc<DynamicImage>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.entries
+            .retain(|((t, p), _)| !(t == title && *p == page));
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(((title.to_string(), page), image));
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+}
+
+impl Manga109 {
+    // Decode the image of a page (by Page::index, as with img_path()), or take it from the cache of
+    // recently decoded pages
+    pub fn load_page_image(&self, book: &str, page: usize) -> Result<PageImage> {
+        if let Some(image) = self.image_cache().get(book, page) {
+            return Ok(PageImage { image });
+        }
+        let path = self.img_path(book, &page)?;
+        let image = Arc::new(image::open(&path).map_err(|e| Error::ImageDecode {
+            path: path.clone().into(),
+            message: e.to_string(),
+        })?);
+        // decoded without holding the lock, so two threads may both decode the same page; the
+        // second insert simply replaces the first
+        self.image_cache().insert(book, page, image.clone());
+        Ok(PageImage { image })
+    }
+
+    // 0 disables the cache
+    pub fn set_image_cache_capacity(&self, capacity: usize) {
+        self.image_cache().set_capacity(capacity);
+    }
+
+    fn image_cache(&self) -> std::sync::MutexGuard<'_, ImageCache> {
+        // the cache is never left half-updated, so a poisoned lock is still usable
+        self.image_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundingBox;
+
+    #[test]
+    fn test_image_cache() {
+        let image = |w| Arc::new(DynamicImage::new_rgb8(w, 1));
+        let mut cache = ImageCache::new(2);
+        cache.insert("A", 0, image(1));
+        cache.insert("A", 1, image(2));
+        assert!(cache.get("A", 0).is_some()); // A/1 is now the least recently used
+        cache.insert("B", 0, image(3));
+        assert!(cache.get("A", 1).is_none());
+        assert_eq!(cache.get("A", 0).unwrap().width(), 1);
+        assert_eq!(cache.get("B", 0).unwrap().width(), 3);
+        cache.set_capacity(0);
+        cache.insert("B", 1, image(4));
+        assert!(cache.get("B", 0).is_none());
+        assert!(cache.get("B", 1).is_none());
+    }
+
+    #[test]
+    fn test_crop() {
+        let page = PageImage {
+            image: Arc::new(DynamicImage::new_rgb8(100, 50)),
+        };
+        let crop = page.crop(&BoundingBox::new(10, 10, 30, 20), 0);
+        assert_eq!((crop.width(), crop.height()), (20, 10));
+        // padded, then clamped to the page
+        let crop = page.crop(&BoundingBox::new(90, 0, 100, 20), 5);
+        assert_eq!((crop.width(), crop.height()), (15, 25));
+        let crop = page.crop(&BoundingBox::new(200, 200, 300, 300), 5);
+        assert_eq!((crop.width(), crop.height()), (0, 0));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{BoundingBox, Frame, HasBoundingBox, Page, Text};
+
+// Minimum fraction of a text box which has to lie within a frame for the text to belong to it;
+// speech balloons regularly stick out of their panel a bit
+pub const DEFAULT_MIN_OVERLAP_RATIO: f32 = 0.5;
+
+// A frame (panel) together with the texts which were assigned to it, in Page::texts order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameTexts<'a> {
+    pub frame: &'a Frame,
+    pub texts: Vec<&'a Text>,
+}
+
+// Result of Page::assign_texts_to_frames(): every Text of the page ends up in exactly one of
+// frames (in Page::frames order, including frames without any text) or outside
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextAssignment<'a> {
+    pub frames: Vec<FrameTexts<'a>>,
+    // captions, sound effects and the like which are drawn across (or in between) panels
+    pub outside: Vec<&'a Text>,
+}
+
+// Fraction of the text box covered by the frame; an empty (zero area) text box counts as fully
+// covered when its center is within the frame
+fn overlap_ratio(text: &BoundingBox, frame: &BoundingBox) -> f32 {
+    if text.is_empty() {
+        let (x, y) = text.center();
+        return if frame.contains_point(x as u32, y as u32) {
+            1.0
+        } else {
+            0.0
+        };
+    }
+    let overlap = text.intersection(frame).map_or(0, |b| b.area());
+    overlap as f32 / text.area() as f32
+}
+
+impl Page {
+    // Position (in Page::frames) of the frame covering the largest part of the box, provided that
+    // part is at least min_overlap_ratio of the box (on a tie, the first frame wins)
+    pub(crate) fn frame_of(&self, bbox: &BoundingBox, min_overlap_ratio: f32) -> Option<usize> {
+        let mut best: Option<(usize, f32)> = None;
+        for (i, frame) in self.frames.iter().enumerate() {
+            let ratio = overlap_ratio(bbox, &frame.bounding_box());
+            if ratio > 0.0 && best.is_none_or(|(_, best_ratio)| ratio > best_ratio) {
+                best = Some((i, ratio));
+            }
+        }
+        best.filter(|(_, ratio)| *ratio >= min_overlap_ratio)
+            .map(|(i, _)| i)
+    }
+
+    // Texts belonging to the frame (compared by value, so a clone of one of this page's frames
+    // works too), see assign_texts_to_frames()
+    pub fn texts_in_frame(&self, frame: &Frame) -> Vec<&Text> {
+        self.assign_texts_to_frames()
+            .frames
+            .into_iter()
+            .find(|frame_texts| frame_texts.frame == frame)
+            .map(|frame_texts| frame_texts.texts)
+            .unwrap_or_default()
+    }
+
+    pub fn assign_texts_to_frames(&self) -> TextAssignment<'_> {
+        self.assign_texts_to_frames_with_ratio(DEFAULT_MIN_OVERLAP_RATIO)
+    }
+
+    // Each text goes to the frame covering the largest part of it, provided that part is at least
+    // min_overlap_ratio of the text box (on a tie, the first frame wins); otherwise it is outside
+    pub fn assign_texts_to_frames_with_ratio(&self, min_overlap_ratio: f32) -> TextAssignment<'_> {
+        let mut assignment = TextAssignment {
+            frames: self
+                .frames
+                .iter()
+                .map(|frame| FrameTexts {
+                    frame,
+                    texts: Vec::new(),
+                })
+                .collect(),
+            outside: Vec::new(),
+        };
+        for text in self.texts.iter() {
+            match self.frame_of(&text.bounding_box(), min_overlap_ratio) {
+                Some(i) => assignment.frames[i].texts.push(text),
+                None => assignment.outside.push(text),
+            }
+        }
+        assignment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_raw_xml_annotations_and_make_book, Text};
+
+    #[test]
+    fn test_assign_texts_to_frames() {
+        let book = parse_raw_xml_annotations_and_make_book(
+            r#"<book title="title"><pages>
+                <page index="0" width="200" height="100">
+                    <frame id="00000001" xmin="100" ymin="0" xmax="200" ymax="100"/>
+                    <frame id="00000002" xmin="0" ymin="0" xmax="100" ymax="100"/>
+                    <frame id="00000003" xmin="0" ymin="0" xmax="10" ymax="10"/>
+                    <text id="0000000a" xmin="120" ymin="10" xmax="140" ymax="50">inside</text>
+                    <text id="0000000b" xmin="90" ymin="10" xmax="130" ymax="50">mostly right</text>
+                    <text id="0000000c" xmin="10" ymin="10" xmax="40" ymax="50">left</text>
+                </page>
+                <page index="1" width="200" height="100">
+                    <frame id="00000004" xmin="0" ymin="0" xmax="100" ymax="100"/>
+                    <text id="0000000e" xmin="60" ymin="10" xmax="140" ymax="50">across</text>
+                    <text id="0000000f" xmin="150" ymin="10" xmax="190" ymax="50">outside</text>
+                </page>
+            </pages></book>"#,
+            "",
+            "",
+        )
+        .unwrap();
+
+        let ids = |texts: &[&Text]| -> Vec<String> { texts.iter().map(|t| t.id.clone()).collect() };
+        let page = &book.pages[0];
+        let assignment = page.assign_texts_to_frames();
+        assert_eq!(assignment.frames.len(), 3);
+        assert_eq!(
+            ids(&assignment.frames[0].texts),
+            vec!["0000000a", "0000000b"]
+        );
+        assert_eq!(ids(&assignment.frames[1].texts), vec!["0000000c"]);
+        assert!(assignment.frames[2].texts.is_empty());
+        assert!(assignment.outside.is_empty());
+        assert_eq!(
+            ids(&page.texts_in_frame(&page.frames[1].clone())),
+            vec!["0000000c"]
+        );
+
+        // half of "across" is within the frame, which is just enough; "outside" is not in any
+        let page = &book.pages[1];
+        let assignment = page.assign_texts_to_frames();
+        assert_eq!(ids(&assignment.frames[0].texts), vec!["0000000e"]);
+        assert_eq!(ids(&assignment.outside), vec!["0000000f"]);
+        let assignment = page.assign_texts_to_frames_with_ratio(0.6);
+        assert!(assignment.frames[0].texts.is_empty());
+        assert_eq!(ids(&assignment.outside), vec!["0000000e", "0000000f"]);
+    }
+}
@@ -0,0 +1,257 @@
+use std::ops::{Bound, RangeBounds};
+
+use regex::Regex;
+
+use crate::{Body, Book, Face, Frame, HasBoundingBox, Manga109, Page, Result, Text};
+
+// An element (or a page) together with the book and page it is on; all references, so these are
+// cheap to pass around and collect
+#[derive(Debug)]
+pub struct Located<'a, T> {
+    pub book: &'a Book,
+    pub page: &'a Page,
+    pub element: &'a T,
+}
+// derive would require T: Clone/Copy
+impl<T> Clone for Located<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for Located<'_, T> {}
+impl<'a, T> Located<'a, T> {
+    pub fn title(&self) -> &'a str {
+        &self.book.title
+    }
+    pub fn page_index(&self) -> usize {
+        self.page.index
+    }
+}
+
+// Composable filters over (already loaded) books, i.e. all the vertical text boxes of at least
+// 32x32 pixels in the first 10 pages of two books:
+//  manga109
+//      .query()?
+//      .books(["ARMS", "AisazuNihaIrarenai"])
+//      .page_range(0..10)
+//      .min_size(32, 32)
+//      .aspect_ratio(1.0..)
+//      .texts()
+// The filters narrow everything they apply to and leave the rest alone: the book and page
+// filters apply to all of them, size and aspect ratio to frames/texts/faces/bodies (not pages),
+// character to faces/bodies only and text_matches to texts only.
+#[derive(Debug, Clone)]
+pub struct Query<'a> {
+    books: Vec<&'a Book>,
+    titles: Option<Vec<String>>,
+    pages: (Bound<usize>, Bound<usize>),
+    min_size: (u32, u32),
+    max_size: (u32, u32),
+    aspect_ratio: (Bound<f32>, Bound<f32>), // height / width
+    character: Option<String>,
+    text: Option<Regex>,
+}
+
+impl<'a> Query<'a> {
+    pub fn new(books: impl IntoIterator<Item = &'a Book>) -> Self {
+        Query {
+            books: books.into_iter().collect(),
+            titles: None,
+            pages: (Bound::Unbounded, Bound::Unbounded),
+            min_size: (0, 0),
+            max_size: (u32::MAX, u32::MAX),
+            aspect_ratio: (Bound::Unbounded, Bound::Unbounded),
+            character: None,
+            text: None,
+        }
+    }
+
+    pub fn book(self, title: &str) -> Self {
+        self.books([title])
+    }
+    // only these titles (called more than once, the titles add up)
+    pub fn books<S: AsRef<str>>(mut self, titles: impl IntoIterator<Item = S>) -> Self {
+        self.titles
+            .get_or_insert_with(Vec::new)
+            .extend(titles.into_iter().map(|title| title.as_ref().to_string()));
+        self
+    }
+    // by Page::index, i.e. page_range(3..=5)
+    pub fn page_range(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.pages = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+    pub fn min_size(mut self, width: u32, height: u32) -> Self {
+        self.min_size = (width, height);
+        self
+    }
+    pub fn max_size(mut self, width: u32, height: u32) -> Self {
+        self.max_size = (width, height);
+        self
+    }
+    // height / width, so aspect_ratio(1.0..) are the vertical (and square) boxes
+    pub fn aspect_ratio(mut self, range: impl RangeBounds<f32>) -> Self {
+        self.aspect_ratio = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+    // faces/bodies of this Character::id
+    pub fn character(mut self, character_id: &str) -> Self {
+        self.character = Some(character_id.to_string());
+        self
+    }
+    // texts whose value matches (anywhere, use ^...$ to match it all)
+    pub fn text_matches(mut self, regex: Regex) -> Self {
+        self.text = Some(regex);
+        self
+    }
+
+    fn matches_box(&self, element: &impl HasBoundingBox) -> bool {
+        let bbox = element.bounding_box();
+        let (width, height) = (bbox.width(), bbox.height());
+        if width < self.min_size.0 || height < self.min_size.1 {
+            return false;
+        }
+        if width > self.max_size.0 || height > self.max_size.1 {
+            return false;
+        }
+        if self.aspect_ratio == (Bound::Unbounded, Bound::Unbounded) {
+            return true;
+        }
+        width > 0 && self.aspect_ratio.contains(&(height as f32 / width as f32))
+    }
+
+    // the pages of the selected books and page range, in book and then page order
+    pub fn pages(&self) -> impl Iterator<Item = Located<'a, Page>> + '_ {
+        self.books
+            .iter()
+            .copied()
+            .filter(|book| {
+                self.titles
+                    .as_ref()
+                    .is_none_or(|titles| titles.contains(&book.title))
+            })
+            .flat_map(|book| book.pages.iter().map(move |page| (book, page)))
+            .filter(|(_, page)| self.pages.contains(&page.index))
+            .map(|(book, page)| Located {
+                book,
+                page,
+                element: page,
+            })
+    }
+
+    fn elements<T: HasBoundingBox + 'a>(
+        &self,
+        elements: impl Fn(&'a Page) -> &'a [T] + 'a,
+    ) -> impl Iterator<Item = Located<'a, T>> + '_ {
+        self.pages().flat_map(move |located| {
+            elements(located.page).iter().map(move |element| Located {
+                book: located.book,
+                page: located.page,
+                element,
+            })
+        })
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = Located<'a, Frame>> + '_ {
+        self.elements(|page| &page.frames)
+            .filter(|located| self.matches_box(located.element))
+    }
+    pub fn texts(&self) -> impl Iterator<Item = Located<'a, Text>> + '_ {
+        self.elements(|page| &page.texts).filter(|located| {
+            self.matches_box(located.element)
+                && self
+                    .text
+                    .as_ref()
+                    .is_none_or(|regex| regex.is_match(&located.element.value))
+        })
+    }
+    pub fn faces(&self) -> impl Iterator<Item = Located<'a, Face>> + '_ {
+        self.elements(|page| &page.faces).filter(|located| {
+            self.matches_box(located.element)
+                && self
+                    .character
+                    .as_ref()
+                    .is_none_or(|character| *character == located.element.character)
+        })
+    }
+    pub fn bodies(&self) -> impl Iterator<Item = Located<'a, Body>> + '_ {
+        self.elements(|page| &page.bodies).filter(|located| {
+            self.matches_box(located.element)
+                && self
+                    .character
+                    .as_ref()
+                    .is_none_or(|character| *character == located.element.character)
+        })
+    }
+}
+
+impl Manga109 {
+    // Query over all the books, loading any book which is not loaded yet; use Query::new() with
+    // i.e. the books of load_all_results() to keep going past books which fail to parse
+    pub fn query(&self) -> Result<Query<'_>> {
+        Ok(Query::new(self.load_all()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_raw_xml_annotations_and_make_book;
+
+    #[test]
+    fn test_query() {
+        let book = |title: &str| {
+            parse_raw_xml_annotations_and_make_book(
+                &format!(
+                    r#"<book title="{}"><pages>
+                    <page index="0" width="400" height="200">
+                        <frame id="00000001" xmin="0" ymin="0" xmax="200" ymax="200"/>
+                        <text id="00000002" xmin="0" ymin="0" xmax="20" ymax="80">はい</text>
+                        <text id="00000003" xmin="0" ymin="0" xmax="80" ymax="20">いいえ</text>
+                        <face id="00000004" xmin="0" ymin="0" xmax="10" ymax="10" character="0000000a"/>
+                        <body id="00000005" xmin="0" ymin="0" xmax="10" ymax="10" character="0000000b"/>
+                    </page>
+                    <page index="1" width="400" height="200">
+                        <text id="00000006" xmin="0" ymin="0" xmax="10" ymax="10">はい</text>
+                    </page>
+                </pages></book>"#,
+                    title
+                ),
+                "",
+                "",
+            )
+            .unwrap()
+        };
+        let books = [book("A"), book("B")];
+        let query = Query::new(books.iter());
+
+        assert_eq!(query.pages().count(), 4);
+        assert_eq!(query.texts().count(), 6);
+        let texts: Vec<(&str, usize, &str)> = query
+            .clone()
+            .book("B")
+            .min_size(20, 20)
+            .texts()
+            .map(|t| (t.title(), t.page_index(), t.element.id.as_str()))
+            .collect();
+        assert_eq!(texts, vec![("B", 0, "00000002"), ("B", 0, "00000003")]);
+
+        let vertical = query.clone().page_range(..1).aspect_ratio(1.0..);
+        assert_eq!(vertical.texts().count(), 2);
+        // the aspect ratio of a square frame is 1.0
+        assert_eq!(vertical.frames().count(), 2);
+
+        let yes = query
+            .clone()
+            .page_range(1..)
+            .text_matches(Regex::new("^はい$").unwrap());
+        assert_eq!(yes.texts().count(), 2);
+        // text_matches only narrows texts
+        assert_eq!(yes.faces().count(), 0); // (no faces on page 1)
+
+        let character = query.clone().character("0000000a");
+        assert_eq!(character.faces().count(), 2);
+        assert_eq!(character.bodies().count(), 0);
+        assert_eq!(character.texts().count(), 6);
+    }
+}
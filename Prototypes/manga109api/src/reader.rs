@@ -0,0 +1,456 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use quick_xml::events::{BytesStart, Event};
+
+use crate::{
+    normalize_paths, AnnotationNode, Book, Character, Error, Manga109, Page, ParseContext,
+    ParseDiagnostic, ParseOptions, Result,
+};
+
+// Streaming alternative to get_book()/Manga109::book(): rather than building the DOM of the whole
+// XML file, the file is read one event at a time and handed out one Page at a time, so only the
+// page being read is ever held in memory:
+//  let mut reader = BookReader::open("annotations/ARMS.xml", &ParseOptions::default())?;
+//  for page in reader.pages() {
+//      let page = page?;
+//      ...
+//  }
+// The pages are parsed exactly the same way (same errors in strict mode, same repairs and
+// diagnostics in lenient mode) as by the DOM parser.
+// NOTE: the <characters> are read up-front (by new()/open()), they come before the <pages> in the
+// annotation schema; any <character> after the first <page> is not read.
+pub struct BookReader<R> {
+    reader: quick_xml::Reader<Position<R>>,
+    path: String, // for Error::Xml, empty when not reading from a file
+    image_root_dir: String,
+    buf: Vec<u8>,
+    ctx: ParseContext,
+    characters: Vec<Character>,
+    pending: Option<Token>, // the first <page>, already read by new()
+    open_elements: usize,
+    done: bool,
+}
+
+// An element as read from the stream, with its text content (only read for the annotation
+// elements, i.e. <text>)
+#[derive(Debug)]
+struct Element {
+    tag: String,
+    attributes: Vec<(String, String)>,
+    text: Option<String>,
+}
+impl AnnotationNode for Element {
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+    fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+}
+
+#[derive(Debug)]
+enum Token {
+    Start(Element),
+    Empty(Element),
+    End(String),
+    Text(String),
+    Other,
+    Eof,
+}
+
+// Keeps track of the line and column quick-xml has read up to, so that Error::Xml can point at
+// where the problem is (quick-xml itself only knows the byte offset)
+struct Position<R> {
+    inner: R,
+    line: u32,
+    column: u32,
+}
+impl<R: BufRead> Read for Position<R> {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+impl<R: BufRead> BufRead for Position<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+    fn consume(&mut self, amount: usize) {
+        if let Ok(consumed) = self.inner.fill_buf() {
+            for &byte in consumed.iter().take(amount) {
+                if byte == b'\n' {
+                    self.line += 1;
+                    self.column = 1;
+                } else if byte & 0xC0 != 0x80 {
+                    // not a UTF-8 continuation byte
+                    self.column += 1;
+                }
+            }
+        }
+        self.inner.consume(amount);
+    }
+}
+
+impl BookReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::MissingAnnotationFile(path.to_path_buf()),
+            _ => Error::Io {
+                path: path.to_path_buf(),
+                source: e,
+            },
+        })?;
+        Self::new(
+            BufReader::new(file),
+            normalize_paths(&path.to_string_lossy()).as_str(),
+            options,
+        )
+    }
+}
+
+impl<R: BufRead> BookReader<R> {
+    // Reads up to the first <page>: the <book> title and the <characters>
+    pub fn new(reader: R, path: &str, options: &ParseOptions) -> Result<Self> {
+        let mut reader = BookReader {
+            reader: quick_xml::Reader::from_reader(Position {
+                inner: reader,
+                line: 1,
+                column: 1,
+            }),
+            path: path.to_string(),
+            image_root_dir: String::new(),
+            buf: Vec::new(),
+            ctx: ParseContext {
+                options: *options,
+                title: String::new(),
+                page: None,
+                diagnostics: Vec::new(),
+            },
+            characters: Vec::new(),
+            pending: None,
+            open_elements: 0,
+            done: false,
+        };
+        let mut root = None;
+        loop {
+            match reader.next_token()? {
+                Token::Start(element) | Token::Empty(element) if root.is_none() => {
+                    // without a title, there is no book to speak of, so this one is always fatal
+                    reader.ctx.title = crate::attribute(&element, &None, "title")?.to_string();
+                    root = Some(element);
+                }
+                Token::Start(element) | Token::Empty(element) if element.tag == "character" => {
+                    if let Some(character) = reader.ctx.parse_character(&element)? {
+                        reader.characters.push(character);
+                    }
+                }
+                token @ (Token::Start(_) | Token::Empty(_)) if is_page(&token) => {
+                    reader.pending = Some(token);
+                    break;
+                }
+                Token::Eof if root.is_none() => return Err(reader.xml_error("no root element")),
+                Token::Eof => {
+                    reader.done = true;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        Ok(reader)
+    }
+
+    pub fn title(&self) -> &str {
+        &self.ctx.title
+    }
+
+    pub fn characters(&self) -> &[Character] {
+        &self.characters
+    }
+
+    // Defects repaired/skipped (lenient mode only) in what has been read so far
+    pub fn diagnostics(&self) -> &[ParseDiagnostic] {
+        &self.ctx.diagnostics
+    }
+
+    // The remaining pages, in document order; the iteration ends after the first error
+    pub fn pages(&mut self) -> Pages<'_, R> {
+        Pages { reader: self }
+    }
+
+    // Reads all the (remaining) pages into a Book, same as get_book_with_options() would
+    pub fn read_book(mut self) -> Result<(Book, Vec<ParseDiagnostic>)> {
+        let pages = self.pages().collect::<Result<Vec<_>>>()?;
+        let mut book = Book {
+            characters: std::mem::take(&mut self.characters),
+            pages,
+            title: self.ctx.title.clone(),
+            annotation_filepaths: self.path.clone(),
+            image_root_dir: self.image_root_dir.clone(),
+            page_positions: Default::default(),
+        };
+        book.index_pages();
+        Ok((book, self.ctx.diagnostics))
+    }
+
+    fn next_page(&mut self) -> Result<Option<Page>> {
+        loop {
+            let token = match self.pending.take() {
+                Some(token) => token,
+                None => self.next_token()?,
+            };
+            let (element, empty) = match token {
+                Token::Start(element) if element.tag == "page" => (element, false),
+                Token::Empty(element) if element.tag == "page" => (element, true),
+                Token::Eof => return Ok(None),
+                _ => continue,
+            };
+            // the elements of a defective (skipped) page still have to be read past
+            let mut page = self
+                .ctx
+                .parse_page(&element)?
+                .map(|(index, width, height)| Page::new(index, width, height));
+            if !empty {
+                self.read_page_elements(page.as_mut())?;
+            }
+            if page.is_some() {
+                return Ok(page);
+            }
+        }
+    }
+
+    // Up to (and including) the closing </page>, adding the elements to the page unless it is None
+    fn read_page_elements(&mut self, mut page: Option<&mut Page>) -> Result<()> {
+        loop {
+            let (mut element, empty) = match self.next_token()? {
+                Token::End(tag) if tag == "page" => return Ok(()),
+                Token::Start(element) => (element, false),
+                Token::Empty(element) => (element, true),
+                Token::Eof => return Err(self.xml_error("unexpected end of file in <page>")),
+                _ => continue,
+            };
+            let Some(page) = page.as_deref_mut() else {
+                continue;
+            };
+            if !matches!(element.tag.as_str(), "frame" | "text" | "face" | "body") {
+                continue;
+            }
+            if !empty {
+                element.text = self.read_text(&element.tag)?;
+            }
+            if let Some(element) = self.ctx.parse_element(&element, page.width, page.height)? {
+                page.push(element);
+            }
+        }
+    }
+
+    // Text content up to the closing tag, None when there is none (like roxmltree::Node::text())
+    fn read_text(&mut self, tag: &str) -> Result<Option<String>> {
+        let mut text: Option<String> = None;
+        loop {
+            match self.next_token()? {
+                Token::Text(value) => text.get_or_insert_with(String::new).push_str(&value),
+                Token::End(end) if end == tag => return Ok(text),
+                Token::Eof => {
+                    return Err(self.xml_error(&format!("unexpected end of file in <{}>", tag)))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token> {
+        self.buf.clear();
+        let token = match self.reader.read_event_into(&mut self.buf) {
+            Ok(Event::Start(start)) => Ok(Token::Start(element(&start))),
+            Ok(Event::Empty(start)) => Ok(Token::Empty(element(&start))),
+            Ok(Event::End(end)) => Ok(Token::End(
+                String::from_utf8_lossy(end.local_name().as_ref()).into_owned(),
+            )),
+            Ok(Event::Text(text)) => text
+                .unescape()
+                .map(|text| Token::Text(text.into_owned()))
+                .map_err(|e| e.to_string()),
+            Ok(Event::CData(cdata)) => Ok(Token::Text(
+                String::from_utf8_lossy(&cdata.into_inner()).into_owned(),
+            )),
+            Ok(Event::Eof) => Ok(Token::Eof),
+            Ok(_) => Ok(Token::Other), // declaration, comment, processing instruction, doctype
+            Err(e) => Err(e.to_string()),
+        };
+        let token = token.map_err(|message| self.xml_error(&message))?;
+        // quick-xml checks that the end tags match, but not that there are any
+        match &token {
+            Token::Start(_) => self.open_elements += 1,
+            Token::End(_) => self.open_elements -= 1,
+            Token::Eof if self.open_elements > 0 => {
+                return Err(self.xml_error("unexpected end of file, unclosed element(s)"))
+            }
+            _ => {}
+        }
+        Ok(token)
+    }
+
+    fn xml_error(&self, message: &str) -> Error {
+        let position = self.reader.get_ref();
+        Error::Xml {
+            path: self.path.clone(),
+            line: position.line,
+            column: position.column,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn is_page(token: &Token) -> bool {
+    matches!(token, Token::Start(element) | Token::Empty(element) if element.tag == "page")
+}
+
+fn element(start: &BytesStart) -> Element {
+    Element {
+        tag: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+        attributes: start
+            .attributes()
+            .filter_map(|attribute| attribute.ok())
+            .map(|attribute| {
+                let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+                let value = match attribute.unescape_value() {
+                    Ok(value) => value.into_owned(),
+                    Err(_) => String::from_utf8_lossy(&attribute.value).into_owned(),
+                };
+                (key, value)
+            })
+            .collect(),
+        text: None,
+    }
+}
+
+pub struct Pages<'r, R> {
+    reader: &'r mut BookReader<R>,
+}
+impl<R: BufRead> Iterator for Pages<'_, R> {
+    type Item = Result<Page>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reader.done {
+            return None;
+        }
+        let page = self.reader.next_page().transpose();
+        if !matches!(page, Some(Ok(_))) {
+            self.reader.done = true;
+        }
+        page
+    }
+}
+
+impl Manga109 {
+    // Streaming reader over the annotation XML of the book (of this Manga109's annotation version),
+    // independent of (and without going through) the lazily loaded books of book()
+    pub fn book_reader(&self, title: &str) -> Result<BookReader<BufReader<File>>> {
+        if !self.titles.iter().any(|t| t == title) {
+            return Err(Error::MissingBook(title.to_string()));
+        }
+        let root_dir = Path::new(&self.root_dir);
+        let xml_path = root_dir
+            .join(self.annotation_version.dir_name())
+            .join(format!("{}.xml", title));
+        let mut reader = BookReader::open(xml_path, &self.options)?;
+        reader.image_root_dir =
+            normalize_paths(&root_dir.join("images").join(title).to_string_lossy());
+        Ok(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_raw_xml_annotations_with_options;
+
+    // the streamed Book (and diagnostics) should be identical to the DOM parsed one
+    fn assert_same_as_dom(xml: &str, options: &ParseOptions) {
+        let streamed = BookReader::new(xml.as_bytes(), "", options).and_then(|r| r.read_book());
+        let parsed = parse_raw_xml_annotations_with_options(xml, "", "", options);
+        match (streamed, parsed) {
+            (Ok(streamed), Ok(parsed)) => assert_eq!(streamed, parsed),
+            (Err(streamed), Err(parsed)) => assert_eq!(streamed.to_string(), parsed.to_string()),
+            (streamed, parsed) => panic!("streamed: {:?}, parsed: {:?}", streamed, parsed),
+        }
+    }
+
+    #[test]
+    fn test_book_reader() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+            <book title="title">
+                <characters>
+                    <character id="0000000a" name="name &amp; surname"/>
+                    <character id="0000000b"/>
+                </characters>
+                <pages>
+                    <page index="0" width="100" height="200">
+                        <text id="00000001" xmin="10" ymin="20" xmax="30" ymax="40">&lt;え&gt;
+ほんと？</text>
+                        <!-- a comment -->
+                        <frame id="00000002" xmin="30" ymin="40" xmax="10" ymax="20"/>
+                        <face id="00000003" xmin="90" ymin="20" xmax="120" ymax="40" character="0000000a"></face>
+                        <text id="00000004" xmin="10" ymin="20" xmax="30" ymax="40"></text>
+                    </page>
+                    <page width="100" height="200">
+                        <frame id="00000005" xmin="0" ymin="0" xmax="10" ymax="10"/>
+                    </page>
+                    <page index="2" width="100" height="200"/>
+                    <page index="3" width="100" height="200">
+                        <body id="00000006" xmin="x" ymin="20" xmax="30" ymax="40" character="0000000a"/>
+                        <text id="00000007" xmin="1" ymin="2" xmax="3" ymax="4"><![CDATA[a<b]]></text>
+                    </page>
+                </pages>
+            </book>"#;
+        assert_same_as_dom(xml, &ParseOptions::default());
+        assert_same_as_dom(xml, &ParseOptions::lenient());
+
+        let mut reader = BookReader::new(xml.as_bytes(), "", &ParseOptions::lenient()).unwrap();
+        assert_eq!(reader.title(), "title");
+        assert_eq!(reader.characters().len(), 1);
+        assert_eq!(reader.characters()[0].name, "name & surname");
+        assert_eq!(reader.diagnostics().len(), 1); // so far, only the character
+        let pages: Vec<Page> = reader.pages().map(|page| page.unwrap()).collect();
+        assert_eq!(
+            pages.iter().map(|page| page.index).collect::<Vec<_>>(),
+            vec![0, 2, 3]
+        );
+        assert_eq!(pages[0].texts[0].value, "<え>\nほんと？");
+        assert_eq!(pages[2].texts[0].value, "a<b");
+        assert!(reader.pages().next().is_none());
+    }
+
+    #[test]
+    fn test_book_reader_errors() {
+        assert_same_as_dom(
+            r#"<book><pages><page index="1" width="100" height="200"/></pages></book>"#,
+            &ParseOptions::default(),
+        );
+
+        let xml = "<book title=\"title\">\n<pages>\n<page index=\"0\" width=\"1\" height=\"1\">\n</pages>";
+        let mut reader =
+            BookReader::new(xml.as_bytes(), "bad.xml", &ParseOptions::default()).unwrap();
+        let mut pages = reader.pages();
+        match pages.next() {
+            Some(Err(Error::Xml { path, line, .. })) => {
+                assert_eq!(path, "bad.xml");
+                assert_eq!(line, 4);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(pages.next().is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{Book, BoundingBox, HasBoundingBox, Page, Text};
+
+// Manga is read right-to-left, top-to-bottom: the tiers (rows of panels) of a page from the top
+// down, and within a tier the panels from the right to the left.  A panel can span several tiers
+// (i.e. a tall panel on the right next to two stacked ones on the left), so rather than grouping
+// into rows this recursively cuts the page (XY-cut):
+//  1. if the boxes can be split by a horizontal line, read everything above it first
+//  2. else if they can be split by a vertical line, read everything right of it first
+//  3. else (no clean cut) take the top-right box first and try again with the rest
+// Neighbouring panels (and balloons) overlap by a few pixels more often than not, so each box is
+// shrunk by CUT_TOLERANCE of its width/height (on every side) when looking for a cut.
+pub const CUT_TOLERANCE: f32 = 0.1;
+
+fn shrink(bbox: &BoundingBox) -> BoundingBox {
+    let dx = (bbox.width() as f32 * CUT_TOLERANCE) as u32;
+    let dy = (bbox.height() as f32 * CUT_TOLERANCE) as u32;
+    BoundingBox {
+        xmin: bbox.xmin + dx,
+        ymin: bbox.ymin + dy,
+        xmax: bbox.xmax - dx,
+        ymax: bbox.ymax - dy,
+    }
+}
+
+// Splits indices into (before, after) the first gap along one axis, given (start, end) of each
+// box on that axis in reading direction; None if there is no gap
+fn split_at_gap(
+    indices: &[usize],
+    span: impl Fn(usize) -> (i64, i64),
+) -> Option<(Vec<usize>, Vec<usize>)> {
+    let mut sorted = indices.to_vec();
+    sorted.sort_by_key(|&i| span(i));
+    let mut end = span(sorted[0]).1;
+    for (position, &i) in sorted.iter().enumerate().skip(1) {
+        let (next_start, next_end) = span(i);
+        if next_start >= end {
+            let after = sorted.split_off(position);
+            return Some((sorted, after));
+        }
+        end = end.max(next_end);
+    }
+    None
+}
+
+fn xy_cut(boxes: &[BoundingBox], indices: Vec<usize>, order: &mut Vec<usize>) {
+    if indices.len() <= 1 {
+        order.extend(indices);
+        return;
+    }
+    // top to bottom
+    if let Some((above, below)) =
+        split_at_gap(&indices, |i| (boxes[i].ymin as i64, boxes[i].ymax as i64))
+    {
+        xy_cut(boxes, above, order);
+        xy_cut(boxes, below, order);
+        return;
+    }
+    // right to left, hence the negated x coordinates
+    if let Some((right, left)) = split_at_gap(&indices, |i| {
+        (-(boxes[i].xmax as i64), -(boxes[i].xmin as i64))
+    }) {
+        xy_cut(boxes, right, order);
+        xy_cut(boxes, left, order);
+        return;
+    }
+    // of the boxes starting above the middle of the top-most box, the right-most one goes first
+    let top = indices
+        .iter()
+        .map(|&i| boxes[i])
+        .min_by_key(|b| b.ymin)
+        .unwrap();
+    let middle = top.ymin + top.height() / 2;
+    let first = indices
+        .iter()
+        .copied()
+        .filter(|&i| boxes[i].ymin <= middle)
+        .max_by_key(|&i| (boxes[i].xmax, std::cmp::Reverse(i)))
+        .unwrap();
+    order.push(first);
+    xy_cut(
+        boxes,
+        indices.into_iter().filter(|&i| i != first).collect(),
+        order,
+    );
+}
+
+// Indices of the boxes in reading order
+pub fn reading_order(boxes: &[BoundingBox]) -> Vec<usize> {
+    let shrunk: Vec<BoundingBox> = boxes.iter().map(shrink).collect();
+    let mut order = Vec::with_capacity(boxes.len());
+    xy_cut(&shrunk, (0..boxes.len()).collect(), &mut order);
+    order
+}
+
+// i.e. sort_in_reading_order(&page.frames) or sort_in_reading_order(page.texts_in_frame(frame))
+pub fn sort_in_reading_order<'a, T, I>(items: I) -> Vec<&'a T>
+where
+    T: HasBoundingBox + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    let items: Vec<&T> = items.into_iter().collect();
+    let boxes: Vec<BoundingBox> = items.iter().map(|item| item.bounding_box()).collect();
+    reading_order(&boxes)
+        .into_iter()
+        .map(|i| items[i])
+        .collect()
+}
+
+// All the texts of the page in the order a reader sees them: frames in reading order, and the
+// texts of each frame (see Page::assign_texts_to_frames()) in reading order within it.  Texts
+// outside of any frame are read where they are on the page, as if they were a frame by themselves.
+pub fn page_transcript(page: &Page) -> Vec<&Text> {
+    let assignment = page.assign_texts_to_frames();
+    let mut units: Vec<(BoundingBox, Vec<&Text>)> = assignment
+        .frames
+        .into_iter()
+        .filter(|frame_texts| !frame_texts.texts.is_empty())
+        .map(|frame_texts| (frame_texts.frame.bounding_box(), frame_texts.texts))
+        .collect();
+    units.extend(
+        assignment
+            .outside
+            .into_iter()
+            .map(|text| (text.bounding_box(), vec![text])),
+    );
+
+    let boxes: Vec<BoundingBox> = units.iter().map(|(bbox, _)| *bbox).collect();
+    let mut transcript = Vec::with_capacity(page.texts.len());
+    for i in reading_order(&boxes) {
+        transcript.extend(sort_in_reading_order(units[i].1.iter().copied()));
+    }
+    transcript
+}
+
+// page_transcript() of every page, pages in the order of their index
+pub fn book_transcript(book: &Book) -> Vec<&Text> {
+    let mut pages: Vec<&Page> = book.pages.iter().collect();
+    pages.sort_by_key(|page| page.index);
+    pages.into_iter().flat_map(page_transcript).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_raw_xml_annotations_and_make_book;
+
+    #[test]
+    fn test_reading_order() {
+        // a tall panel on the right next to two stacked ones on the left, then a full width tier
+        // below; every panel overlaps its neighbours by a couple of pixels
+        let boxes = [
+            BoundingBox::new(0, 0, 102, 52),    // top left
+            BoundingBox::new(0, 200, 200, 300), // bottom tier
+            BoundingBox::new(98, 0, 200, 202),  // tall right
+            BoundingBox::new(0, 48, 102, 202),  // middle left
+        ];
+        assert_eq!(reading_order(&boxes), vec![2, 0, 3, 1]);
+
+        // no clean cut at all: top-right first
+        let boxes = [
+            BoundingBox::new(0, 10, 60, 50),
+            BoundingBox::new(40, 0, 100, 40),
+            BoundingBox::new(20, 30, 80, 90),
+        ];
+        assert_eq!(reading_order(&boxes), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_page_transcript() {
+        let book = parse_raw_xml_annotations_and_make_book(
+            r#"<book title="title"><pages>
+                <page index="1" width="200" height="200">
+                    <frame id="00000002" xmin="0" ymin="0" xmax="200" ymax="200"/>
+                    <text id="0000000e" xmin="10" ymin="10" xmax="40" ymax="80">last</text>
+                </page>
+                <page index="0" width="200" height="200">
+                    <frame id="00000001" xmin="0" ymin="110" xmax="200" ymax="200"/>
+                    <frame id="00000000" xmin="0" ymin="0" xmax="200" ymax="90"/>
+                    <text id="0000000a" xmin="10" ymin="120" xmax="40" ymax="180">bottom</text>
+                    <text id="0000000b" xmin="10" ymin="10" xmax="40" ymax="80">top left</text>
+                    <text id="0000000c" xmin="150" ymin="10" xmax="180" ymax="80">top right</text>
+                    <text id="0000000d" xmin="90" ymin="85" xmax="110" ymax="115">between</text>
+                </page>
+            </pages></book>"#,
+            "",
+            "",
+        )
+        .unwrap();
+        let values: Vec<&str> = book_transcript(&book)
+            .iter()
+            .map(|text| text.value.as_str())
+            .collect();
+        assert_eq!(
+            values,
+            vec!["top right", "top left", "between", "bottom", "last"]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use crate::{BoundingBox, Character, HasBoundingBox, Page, Text, DEFAULT_MIN_OVERLAP_RATIO};
+
+// Heuristic speaker attribution: a speech balloon is usually drawn right next to (or on top of)
+// the head of whoever is speaking, and (almost) always in the same frame.  So each Text is scored
+// against the faces and bodies within its own frame (the whole page for texts outside of any
+// frame):
+//  - the closer the box, the higher the score, decaying with the distance between the two boxes
+//    relative to the size of the frame
+//  - faces count more than bodies, and a balloon above or beside a face more than one below it
+// A character's score is the best of its faces/bodies, and the confidence is its share of all the
+// scores plus OFF_PANEL_SCORE, which stands for the speaker not being drawn at all (narration,
+// someone off-panel, a phone call, ...).  So a single face right next to the balloon gets a high
+// but not a 100% confidence, and a lone face on the other side of the frame a low one.
+const FACE_WEIGHT: f32 = 1.0;
+const BODY_WEIGHT: f32 = 0.6;
+const BELOW_PENALTY: f32 = 0.7; // balloon entirely below the face/body
+const OFF_PANEL_SCORE: f32 = 0.2;
+// distance (as a fraction of the frame diagonal) at which the score has dropped to 1/e
+const DISTANCE_SCALE: f32 = 0.25;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerCandidate<'c> {
+    pub character: &'c Character,
+    pub confidence: f32, // 0.0 .. 1.0, the confidences of all candidates add up to less than 1.0
+}
+
+// The candidates for one Text, most likely speaker first
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerAttribution<'p, 'c> {
+    pub text: &'p Text,
+    pub candidates: Vec<SpeakerCandidate<'c>>,
+}
+
+fn score(text: &BoundingBox, other: &BoundingBox, weight: f32, scope_diagonal: f32) -> f32 {
+    let distance = text.distance(other) / scope_diagonal.max(1.0);
+    let mut score = weight * (-distance / DISTANCE_SCALE).exp();
+    if text.ymin >= other.ymax {
+        score *= BELOW_PENALTY;
+    }
+    score
+}
+
+impl Page {
+    // Ranked candidates for who says the text (which should be one of this page's texts); the
+    // characters are usually Book::characters, faces/bodies with a character id which is not in
+    // there are ignored
+    pub fn speaker_candidates<'c>(
+        &self,
+        text: &Text,
+        characters: &'c [Character],
+    ) -> Vec<SpeakerCandidate<'c>> {
+        let text_box = text.bounding_box();
+        let frame = self.frame_of(&text_box, DEFAULT_MIN_OVERLAP_RATIO);
+        let scope = match frame {
+            Some(i) => self.frames[i].bounding_box(),
+            None => BoundingBox::new(0, 0, self.width, self.height),
+        };
+        let scope_diagonal = (scope.width() as f32).hypot(scope.height() as f32);
+
+        let faces = self
+            .faces
+            .iter()
+            .map(|face| (face.bounding_box(), face.character.as_str(), FACE_WEIGHT));
+        let bodies = self
+            .bodies
+            .iter()
+            .map(|body| (body.bounding_box(), body.character.as_str(), BODY_WEIGHT));
+        let mut best: HashMap<&str, f32> = HashMap::new();
+        for (bbox, character, weight) in faces.chain(bodies) {
+            // only within the same frame (or likewise outside of any frame)
+            if self.frame_of(&bbox, DEFAULT_MIN_OVERLAP_RATIO) != frame {
+                continue;
+            }
+            let score = score(&text_box, &bbox, weight, scope_diagonal);
+            let entry = best.entry(character).or_insert(0.0);
+            *entry = entry.max(score);
+        }
+
+        // an unknown character can not be a candidate, so it does not take a share of the total
+        best.retain(|id, _| characters.iter().any(|character| character.id == *id));
+        let total: f32 = best.values().sum::<f32>() + OFF_PANEL_SCORE;
+        let mut candidates: Vec<SpeakerCandidate> = characters
+            .iter()
+            .filter_map(|character| {
+                best.get(character.id.as_str())
+                    .map(|score| SpeakerCandidate {
+                        character,
+                        confidence: score / total,
+                    })
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        candidates
+    }
+
+    // speaker_candidates() of every text on the page, in Page::texts order
+    pub fn attribute_speakers<'c>(
+        &self,
+        characters: &'c [Character],
+    ) -> Vec<SpeakerAttribution<'_, 'c>> {
+        self.texts
+            .iter()
+            .map(|text| SpeakerAttribution {
+                text,
+                candidates: self.speaker_candidates(text, characters),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_raw_xml_annotations_and_make_book;
+
+    #[test]
+    fn test_speaker_candidates() {
+        let book = parse_raw_xml_annotations_and_make_book(
+            r#"<book title="title">
+                <characters>
+                    <character id="0000000a" name="Near"/>
+                    <character id="0000000b" name="Far"/>
+                    <character id="0000000c" name="Left"/>
+                </characters>
+                <pages>
+                <page index="0" width="400" height="200">
+                    <frame id="00000001" xmin="200" ymin="0" xmax="400" ymax="200"/>
+                    <frame id="00000002" xmin="0" ymin="0" xmax="200" ymax="200"/>
+                    <text id="00000010" xmin="340" ymin="10" xmax="370" ymax="80">near</text>
+                    <face id="00000011" xmin="300" ymin="60" xmax="340" ymax="100" character="0000000a"/>
+                    <body id="00000012" xmin="290" ymin="60" xmax="350" ymax="200" character="0000000a"/>
+                    <face id="00000013" xmin="210" ymin="150" xmax="240" ymax="190" character="0000000b"/>
+                    <face id="00000014" xmin="100" ymin="60" xmax="140" ymax="100" character="0000000c"/>
+                    <text id="00000015" xmin="10" ymin="150" xmax="40" ymax="190">below</text>
+                    <face id="00000016" xmin="20" ymin="10" xmax="60" ymax="50" character="0000000d"/>
+                </page>
+            </pages></book>"#,
+            "",
+            "",
+        )
+        .unwrap();
+        let page = &book.pages[0];
+        let attributions = page.attribute_speakers(&book.characters);
+        assert_eq!(attributions.len(), 2);
+
+        let candidates = &attributions[0].candidates;
+        let names: Vec<&str> = candidates
+            .iter()
+            .map(|c| c.character.name.as_str())
+            .collect();
+        // the face in the other frame is never a candidate
+        assert_eq!(names, vec!["Near", "Far"]);
+        assert!(candidates[0].confidence > 0.5);
+        assert!(candidates[1].confidence < 0.2);
+        assert!(candidates.iter().map(|c| c.confidence).sum::<f32>() < 1.0);
+
+        // the closest face is of a character which is not in the book's character list
+        let candidates = &attributions[1].candidates;
+        let names: Vec<&str> = candidates
+            .iter()
+            .map(|c| c.character.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Left"]);
+        // and it does not lower the confidence of the others either
+        let mut without_dangling = page.clone();
+        without_dangling
+            .faces
+            .retain(|face| face.character != "0000000d");
+        let expected = without_dangling.speaker_candidates(&page.texts[1], &book.characters);
+        assert_eq!(candidates, &expected);
+    }
+}
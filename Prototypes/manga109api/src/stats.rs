@@ -0,0 +1,434 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use crate::{Book, HasBoundingBox};
+
+// Bin widths of the histograms
+const TEXTS_PER_PAGE_BIN: f64 = 1.0;
+const TEXT_SIZE_BIN: f64 = 32.0; // pixels
+const TEXT_ASPECT_RATIO_BIN: f64 = 0.25; // height / width
+const TEXT_LENGTH_BIN: f64 = 5.0; // characters
+const FACES_PER_CHARACTER_BIN: f64 = 10.0;
+
+// Count, min/max/mean and a histogram of some value; bin i counts the values within
+// [i * bin_width, (i + 1) * bin_width)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Distribution {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub bin_width: f64,
+    pub histogram: Vec<usize>,
+}
+
+impl Distribution {
+    pub fn new(bin_width: f64) -> Self {
+        Distribution {
+            count: 0,
+            min: 0.0,
+            max: 0.0,
+            mean: 0.0,
+            bin_width,
+            histogram: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1;
+        self.mean += (value - self.mean) / self.count as f64;
+        let bin = (value.max(0.0) / self.bin_width) as usize;
+        if self.histogram.len() <= bin {
+            self.histogram.resize(bin + 1, 0);
+        }
+        self.histogram[bin] += 1;
+    }
+
+    // both have to have the same bin_width
+    pub fn merge(&mut self, other: &Distribution) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other.clone();
+            return;
+        }
+        let count = self.count + other.count;
+        self.mean =
+            (self.mean * self.count as f64 + other.mean * other.count as f64) / count as f64;
+        self.count = count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        if self.histogram.len() < other.histogram.len() {
+            self.histogram.resize(other.histogram.len(), 0);
+        }
+        for (bin, n) in other.histogram.iter().enumerate() {
+            self.histogram[bin] += n;
+        }
+    }
+}
+
+// Occurrences and distinct characters of one script within the text values
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ScriptCounts {
+    pub occurrences: usize,
+    pub unique: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    chars: BTreeSet<char>,
+}
+
+impl ScriptCounts {
+    fn add(&mut self, c: char) {
+        self.occurrences += 1;
+        self.chars.insert(c);
+        self.unique = self.chars.len();
+    }
+
+    fn merge(&mut self, other: &ScriptCounts) {
+        self.occurrences += other.occurrences;
+        self.chars.extend(other.chars.iter().copied());
+        self.unique = self.chars.len();
+    }
+
+    // the distinct characters, i.e. to build the charset of an OCR model
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.chars.iter().copied()
+    }
+}
+
+fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' | '々')
+}
+fn is_hiragana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{309F}')
+}
+fn is_katakana(c: char) -> bool {
+    matches!(c, '\u{30A0}'..='\u{30FF}' | '\u{FF66}'..='\u{FF9D}')
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Stats {
+    pub books: usize,
+    pub pages: usize,
+    pub frames: usize,
+    pub texts: usize,
+    pub faces: usize,
+    pub bodies: usize,
+    pub texts_per_page: Distribution,
+    pub text_width: Distribution,
+    pub text_height: Distribution,
+    pub text_aspect_ratio: Distribution, // height / width, so > 1.0 is vertical text
+    pub vertical_texts: usize,
+    pub horizontal_texts: usize,   // including the square ones
+    pub text_length: Distribution, // characters per text, whitespace not counted
+    pub kanji: ScriptCounts,
+    pub hiragana: ScriptCounts,
+    pub katakana: ScriptCounts,
+    pub faces_per_character: Distribution,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            books: 0,
+            pages: 0,
+            frames: 0,
+            texts: 0,
+            faces: 0,
+            bodies: 0,
+            texts_per_page: Distribution::new(TEXTS_PER_PAGE_BIN),
+            text_width: Distribution::new(TEXT_SIZE_BIN),
+            text_height: Distribution::new(TEXT_SIZE_BIN),
+            text_aspect_ratio: Distribution::new(TEXT_ASPECT_RATIO_BIN),
+            vertical_texts: 0,
+            horizontal_texts: 0,
+            text_length: Distribution::new(TEXT_LENGTH_BIN),
+            kanji: ScriptCounts::default(),
+            hiragana: ScriptCounts::default(),
+            katakana: ScriptCounts::default(),
+            faces_per_character: Distribution::new(FACES_PER_CHARACTER_BIN),
+        }
+    }
+}
+
+impl Stats {
+    pub fn merge(&mut self, other: &Stats) {
+        self.books += other.books;
+        self.pages += other.pages;
+        self.frames += other.frames;
+        self.texts += other.texts;
+        self.faces += other.faces;
+        self.bodies += other.bodies;
+        self.texts_per_page.merge(&other.texts_per_page);
+        self.text_width.merge(&other.text_width);
+        self.text_height.merge(&other.text_height);
+        self.text_aspect_ratio.merge(&other.text_aspect_ratio);
+        self.vertical_texts += other.vertical_texts;
+        self.horizontal_texts += other.horizontal_texts;
+        self.text_length.merge(&other.text_length);
+        self.kanji.merge(&other.kanji);
+        self.hiragana.merge(&other.hiragana);
+        self.katakana.merge(&other.katakana);
+        self.faces_per_character.merge(&other.faces_per_character);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BookStats {
+    pub title: String,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub stats: Stats,
+    // keyed by Character::name, characters without any face are left out
+    pub faces_by_character: BTreeMap<String, usize>,
+}
+
+pub fn book_stats(book: &Book) -> BookStats {
+    let mut stats = Stats {
+        books: 1,
+        pages: book.pages.len(),
+        ..Default::default()
+    };
+    for page in book.pages.iter() {
+        stats.frames += page.frames.len();
+        stats.texts += page.texts.len();
+        stats.faces += page.faces.len();
+        stats.bodies += page.bodies.len();
+        stats.texts_per_page.add(page.texts.len() as f64);
+        for text in page.texts.iter() {
+            let bbox = text.bounding_box();
+            stats.text_width.add(bbox.width() as f64);
+            stats.text_height.add(bbox.height() as f64);
+            if bbox.width() > 0 {
+                stats
+                    .text_aspect_ratio
+                    .add(bbox.height() as f64 / bbox.width() as f64);
+            }
+            if bbox.height() > bbox.width() {
+                stats.vertical_texts += 1;
+            } else {
+                stats.horizontal_texts += 1;
+            }
+            let mut length = 0;
+            for c in text.value.chars().filter(|c| !c.is_whitespace()) {
+                length += 1;
+                if is_kanji(c) {
+                    stats.kanji.add(c);
+                } else if is_hiragana(c) {
+                    stats.hiragana.add(c);
+                } else if is_katakana(c) {
+                    stats.katakana.add(c);
+                }
+            }
+            stats.text_length.add(length as f64);
+        }
+    }
+
+    let mut faces_by_id: BTreeMap<&str, usize> = BTreeMap::new();
+    for face in book.pages.iter().flat_map(|page| page.faces.iter()) {
+        *faces_by_id.entry(face.character.as_str()).or_default() += 1;
+    }
+    let mut faces_by_character = BTreeMap::new();
+    for character in book.characters.iter() {
+        if let Some(&faces) = faces_by_id.get(character.id.as_str()) {
+            stats.faces_per_character.add(faces as f64);
+            faces_by_character.insert(character.name.clone(), faces);
+        }
+    }
+
+    BookStats {
+        title: book.title.clone(),
+        stats,
+        faces_by_character,
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+    pub books: Vec<BookStats>,
+    pub total: Stats,
+}
+
+// Per book and corpus stats, the books in the order given
+pub fn report<'a>(books: impl IntoIterator<Item = &'a Book>) -> Report {
+    let mut report = Report::default();
+    for book in books {
+        let book_stats = book_stats(book);
+        report.total.merge(&book_stats.stats);
+        report.books.push(book_stats);
+    }
+    report
+}
+
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    part as f64 * 100.0 / total as f64
+}
+
+fn write_distribution(md: &mut String, name: &str, distribution: &Distribution) {
+    let _ = writeln!(
+        md,
+        "### {}\n\ncount {}, min {:.2}, max {:.2}, mean {:.2}\n",
+        name, distribution.count, distribution.min, distribution.max, distribution.mean
+    );
+    if distribution.count == 0 {
+        return;
+    }
+    md.push_str("| bin | count | |\n|---|---:|---|\n");
+    let most = distribution
+        .histogram
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    for (bin, &n) in distribution.histogram.iter().enumerate() {
+        if n == 0 {
+            continue;
+        }
+        let from = bin as f64 * distribution.bin_width;
+        let _ = writeln!(
+            md,
+            "| {} - {} | {} | {} |",
+            from,
+            from + distribution.bin_width,
+            n,
+            "#".repeat((n * 40).div_ceil(most))
+        );
+    }
+    md.push('\n');
+}
+
+impl Report {
+    pub fn to_markdown(&self) -> String {
+        let total = &self.total;
+        let mut md = String::from("# Manga109 statistics\n\n");
+        md.push_str("| books | pages | frames | texts | faces | bodies |\n");
+        md.push_str("|---:|---:|---:|---:|---:|---:|\n");
+        let _ = writeln!(
+            md,
+            "| {} | {} | {} | {} | {} | {} |\n",
+            total.books, total.pages, total.frames, total.texts, total.faces, total.bodies
+        );
+        let _ = writeln!(
+            md,
+            "Vertical texts: {} ({:.1}%), horizontal texts: {} ({:.1}%)\n",
+            total.vertical_texts,
+            percent(total.vertical_texts, total.texts),
+            total.horizontal_texts,
+            percent(total.horizontal_texts, total.texts)
+        );
+        md.push_str("| script | occurrences | unique |\n|---|---:|---:|\n");
+        for (name, counts) in [
+            ("kanji", &total.kanji),
+            ("hiragana", &total.hiragana),
+            ("katakana", &total.katakana),
+        ] {
+            let _ = writeln!(
+                md,
+                "| {} | {} | {} |",
+                name, counts.occurrences, counts.unique
+            );
+        }
+        md.push_str("\n## Corpus\n\n");
+        write_distribution(&mut md, "Texts per page", &total.texts_per_page);
+        write_distribution(&mut md, "Text width (px)", &total.text_width);
+        write_distribution(&mut md, "Text height (px)", &total.text_height);
+        write_distribution(
+            &mut md,
+            "Text aspect ratio (height / width)",
+            &total.text_aspect_ratio,
+        );
+        write_distribution(&mut md, "Characters per text", &total.text_length);
+        write_distribution(&mut md, "Faces per character", &total.faces_per_character);
+
+        md.push_str("## Books\n\n");
+        md.push_str("| title | pages | texts | texts/page | vertical | chars/text | kanji | hiragana | katakana | faces |\n");
+        md.push_str("|---|---:|---:|---:|---:|---:|---:|---:|---:|---:|\n");
+        for book in self.books.iter() {
+            let stats = &book.stats;
+            let _ = writeln!(
+                md,
+                "| {} | {} | {} | {:.1} | {:.1}% | {:.1} | {} | {} | {} | {} |",
+                book.title,
+                stats.pages,
+                stats.texts,
+                stats.texts_per_page.mean,
+                percent(stats.vertical_texts, stats.texts),
+                stats.text_length.mean,
+                stats.kanji.unique,
+                stats.hiragana.unique,
+                stats.katakana.unique,
+                stats.faces
+            );
+        }
+        md
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_raw_xml_annotations_and_make_book;
+
+    #[test]
+    fn test_report() {
+        let book = parse_raw_xml_annotations_and_make_book(
+            r#"<book title="title">
+                <characters>
+                    <character id="0000000a" name="Hero"/>
+                    <character id="0000000b" name="Nobody"/>
+                </characters>
+                <pages>
+                <page index="0" width="400" height="200">
+                    <text id="00000001" xmin="0" ymin="0" xmax="30" ymax="90">漢字と カナ</text>
+                    <text id="00000002" xmin="0" ymin="0" xmax="100" ymax="20">ひらがなと漢字</text>
+                    <face id="00000003" xmin="0" ymin="0" xmax="10" ymax="10" character="0000000a"/>
+                    <face id="00000004" xmin="0" ymin="0" xmax="10" ymax="10" character="0000000a"/>
+                </page>
+                <page index="1" width="400" height="200"/>
+            </pages></book>"#,
+            "",
+            "",
+        )
+        .unwrap();
+        let report = report([&book, &book]);
+        let stats = &report.books[0].stats;
+        assert_eq!((stats.pages, stats.texts, stats.faces), (2, 2, 2));
+        assert_eq!(stats.texts_per_page.histogram, vec![1, 0, 1]);
+        assert_eq!(stats.texts_per_page.mean, 1.0);
+        assert_eq!((stats.vertical_texts, stats.horizontal_texts), (1, 1));
+        // 5 and 7 characters
+        assert_eq!(stats.text_length.histogram, vec![0, 2]);
+        // 漢 and 字 twice each, と (twice) is hiragana
+        assert_eq!((stats.kanji.occurrences, stats.kanji.unique), (4, 2));
+        assert_eq!(stats.hiragana.unique, 5);
+        assert_eq!(stats.katakana.chars().collect::<String>(), "カナ");
+        assert_eq!(
+            report.books[0].faces_by_character,
+            BTreeMap::from([("Hero".to_string(), 2)])
+        );
+
+        assert_eq!((report.total.books, report.total.texts), (2, 4));
+        assert_eq!(report.total.texts_per_page.histogram, vec![2, 0, 2]);
+        assert_eq!(report.total.kanji.unique, 2);
+        assert_eq!(report.total.faces_per_character.count, 2);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| 2 | 4 | 0 | 4 | 4 | 0 |"));
+        assert!(markdown.contains("| title | 2 | 2 | 1.0 | 50.0% | 6.0 | 2 | 5 | 2 | 2 |"));
+    }
+}
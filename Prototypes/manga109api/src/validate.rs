@@ -0,0 +1,394 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::{
+    get_book_with_options, AnnotationVersion, Book, Error, HasBoundingBox, PageElement,
+    ParseOptions, Result,
+};
+
+// Lints a whole dataset root, rather than stopping at the first problem the way Manga109::new()
+// and strict parsing do.  Every problem found is one Issue, the books are parsed leniently so that
+// all of their defects come back (as Check::Defect) instead of only the first one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Check {
+    MissingAnnotationFile, // title in books.txt without <title>.xml
+    MissingImageDir,       // title in books.txt without images/<title>/
+    Parse,                 // the XML could not be parsed at all
+    Defect,                // defect found by the (lenient) parser, see ParseDiagnostic
+    MissingImage,          // <page index="N"> without images/<title>/NNN.jpg
+    UnreadableImage,       // NNN.jpg is not a JPEG we can find the dimensions of
+    ImageSize,             // image dimensions differ from the <page> width/height
+    EmptyBox,              // zero width and/or height
+    DuplicateId,           // same id used by more than one character/element of the book
+    UnknownCharacter,      // face/body character which is not one of the book's <characters>
+    UnannotatedImage,      // NNN.jpg in images/<title>/ without a <page> entry
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Issue {
+    pub check: Check,
+    pub title: String,
+    pub page: Option<usize>,
+    pub id: Option<String>, // element id, if the issue is about one element
+    pub message: String,
+}
+
+// (width, height) from the SOF (start of frame) segment of a JPEG file, without decoding it
+fn jpeg_dimensions(path: &Path) -> std::io::Result<(u32, u32)> {
+    let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    let mut byte = [0u8; 1];
+    let mut read_u8 = |reader: &mut BufReader<std::fs::File>| -> std::io::Result<u8> {
+        reader.read_exact(&mut byte)?;
+        Ok(byte[0])
+    };
+    if read_u8(&mut reader)? != 0xFF || read_u8(&mut reader)? != 0xD8 {
+        return Err(invalid("not a JPEG file"));
+    }
+    loop {
+        if read_u8(&mut reader)? != 0xFF {
+            return Err(invalid("corrupt JPEG marker"));
+        }
+        let mut marker = read_u8(&mut reader)?;
+        while marker == 0xFF {
+            marker = read_u8(&mut reader)?; // fill bytes
+        }
+        // markers without a segment
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            continue;
+        }
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length)?;
+        let length = u16::from_be_bytes(length) as u64;
+        // SOF0..SOF15, apart from DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            let mut sof = [0u8; 5]; // precision, height, width
+            reader.read_exact(&mut sof)?;
+            let height = u16::from_be_bytes([sof[1], sof[2]]) as u32;
+            let width = u16::from_be_bytes([sof[3], sof[4]]) as u32;
+            return Ok((width, height));
+        }
+        if marker == 0xDA {
+            return Err(invalid("no SOF segment before the image data"));
+        }
+        std::io::copy(
+            &mut reader.by_ref().take(length.saturating_sub(2)),
+            &mut std::io::sink(),
+        )?;
+    }
+}
+
+// NNN.jpg, the name (see Manga109::img_path()) of the image of <page index="N">; anything else in
+// an image directory (.DS_Store, Thumbs.db, previews, ...) is none of our business
+fn is_page_image_name(name: &str) -> bool {
+    name.strip_suffix(".jpg")
+        .is_some_and(|stem| stem.len() >= 3 && stem.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+struct Issues<'t> {
+    title: &'t str,
+    issues: Vec<Issue>,
+}
+
+impl Issues<'_> {
+    fn push(&mut self, check: Check, page: Option<usize>, id: Option<&str>, message: String) {
+        self.issues.push(Issue {
+            check,
+            title: self.title.to_string(),
+            page,
+            id: id.map(|id| id.to_string()),
+            message,
+        });
+    }
+}
+
+fn validate_book(book: &Book, image_dir: &Path, issues: &mut Issues) {
+    let characters: BTreeSet<&str> = book.characters.iter().map(|c| c.id.as_str()).collect();
+    let mut characters_of_id: HashMap<&str, usize> = HashMap::new();
+    let mut pages_of_id: HashMap<&str, Vec<usize>> = HashMap::new();
+    for character in book.characters.iter() {
+        *characters_of_id.entry(&character.id).or_default() += 1;
+        pages_of_id.entry(&character.id).or_default();
+    }
+    for page in book.pages.iter() {
+        let image_path = image_dir.join(format!("{:03}.jpg", page.index));
+        if !image_path.exists() {
+            issues.push(
+                Check::MissingImage,
+                Some(page.index),
+                None,
+                format!("missing image '{}'", image_path.display()),
+            );
+        } else {
+            match jpeg_dimensions(&image_path) {
+                Ok((width, height)) if (width, height) != (page.width, page.height) => issues.push(
+                    Check::ImageSize,
+                    Some(page.index),
+                    None,
+                    format!(
+                        "image '{}' is {}x{}, but the page is {}x{}",
+                        image_path.display(),
+                        width,
+                        height,
+                        page.width,
+                        page.height
+                    ),
+                ),
+                Ok(_) => {}
+                Err(e) => issues.push(
+                    Check::UnreadableImage,
+                    Some(page.index),
+                    None,
+                    format!("unable to read '{}': {}", image_path.display(), e),
+                ),
+            }
+        }
+
+        for element in page.elements() {
+            let id = element.id();
+            let bbox = element.bounding_box();
+            if bbox.is_empty() {
+                issues.push(
+                    Check::EmptyBox,
+                    Some(page.index),
+                    Some(id),
+                    format!(
+                        "<{}> has an empty bounding box {:?}",
+                        element.kind().tag(),
+                        bbox.to_xyxy()
+                    ),
+                );
+            }
+            let character = match &element {
+                PageElement::Face(face) => Some(face.character.as_str()),
+                PageElement::Body(body) => Some(body.character.as_str()),
+                _ => None,
+            };
+            if let Some(character) = character {
+                if !characters.contains(character) {
+                    issues.push(
+                        Check::UnknownCharacter,
+                        Some(page.index),
+                        Some(id),
+                        format!(
+                            "<{}> refers to unknown character '{}'",
+                            element.kind().tag(),
+                            character
+                        ),
+                    );
+                }
+            }
+        }
+        for id in page
+            .frames
+            .iter()
+            .map(|e| e.id.as_str())
+            .chain(page.texts.iter().map(|e| e.id.as_str()))
+            .chain(page.faces.iter().map(|e| e.id.as_str()))
+            .chain(page.bodies.iter().map(|e| e.id.as_str()))
+        {
+            pages_of_id.entry(id).or_default().push(page.index);
+        }
+    }
+
+    // ids are unique within a book, across characters and all the elements of all the pages
+    let mut duplicates: Vec<(&str, usize, &Vec<usize>)> = pages_of_id
+        .iter()
+        .map(|(id, pages)| (*id, characters_of_id.get(id).copied().unwrap_or(0), pages))
+        .filter(|(_, characters, pages)| characters + pages.len() > 1)
+        .collect();
+    duplicates.sort();
+    for (id, characters, pages) in duplicates {
+        issues.push(
+            Check::DuplicateId,
+            pages.first().copied(),
+            Some(id),
+            format!(
+                "id '{}' is used more than once ({} <character>, pages {:?})",
+                id, characters, pages
+            ),
+        );
+    }
+
+    let annotated: BTreeSet<String> = book
+        .pages
+        .iter()
+        .map(|page| format!("{:03}.jpg", page.index))
+        .collect();
+    let Ok(entries) = std::fs::read_dir(image_dir) else {
+        return;
+    };
+    let mut unannotated: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| is_page_image_name(name) && !annotated.contains(name))
+        .collect();
+    unannotated.sort();
+    for name in unannotated {
+        issues.push(
+            Check::UnannotatedImage,
+            None,
+            None,
+            format!(
+                "image '{}' has no <page> entry",
+                image_dir.join(name).display()
+            ),
+        );
+    }
+}
+
+// Every Issue of the dataset, book by book in books.txt order; Err only when there is no dataset
+// to speak of (no root dir, books.txt, annotations or images directory)
+pub fn validate(root_dir: &str, annotation_version: &AnnotationVersion) -> Result<Vec<Issue>> {
+    let root_path = Path::new(root_dir);
+    if !root_path.exists() {
+        return Err(Error::MissingRootDir(root_path.to_path_buf()));
+    }
+    let books_txt = root_path.join("books.txt");
+    let titles = std::fs::read_to_string(&books_txt).map_err(|e| Error::Io {
+        path: books_txt.clone(),
+        source: e,
+    })?;
+    let annotations_root_dir = root_path.join(annotation_version.dir_name());
+    if !annotations_root_dir.exists() {
+        return Err(Error::MissingAnnotationsDir(annotations_root_dir));
+    }
+    let images_root_dir = root_path.join("images");
+    if !images_root_dir.exists() {
+        return Err(Error::MissingImagesDir(images_root_dir));
+    }
+
+    let mut all_issues = Vec::new();
+    for title in titles.lines() {
+        let mut issues = Issues {
+            title,
+            issues: Vec::new(),
+        };
+        let xml_path = annotations_root_dir.join(format!("{}.xml", title));
+        let image_dir = images_root_dir.join(title);
+        if !xml_path.exists() {
+            issues.push(
+                Check::MissingAnnotationFile,
+                None,
+                None,
+                format!("missing annotation file '{}'", xml_path.display()),
+            );
+        }
+        if !image_dir.exists() {
+            issues.push(
+                Check::MissingImageDir,
+                None,
+                None,
+                format!("missing image directory '{}'", image_dir.display()),
+            );
+        }
+        if xml_path.exists() && image_dir.exists() {
+            match get_book_with_options(
+                root_dir,
+                title,
+                annotation_version,
+                &ParseOptions::lenient(),
+            ) {
+                Ok((book, diagnostics)) => {
+                    for diagnostic in diagnostics {
+                        issues.push(
+                            Check::Defect,
+                            diagnostic.page,
+                            diagnostic.id.as_deref(),
+                            diagnostic.to_string(),
+                        );
+                    }
+                    validate_book(&book, &image_dir, &mut issues);
+                }
+                Err(e) => issues.push(Check::Parse, None, None, e.to_string()),
+            }
+        }
+        all_issues.append(&mut issues.issues);
+    }
+    Ok(all_issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // smallest possible JPEG header: SOI, an APP0 segment to skip, then SOF0
+    fn write_jpeg_header(path: &Path, width: u16, height: u16) {
+        let mut jpeg = vec![
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x0B, 0x08,
+        ];
+        jpeg.extend_from_slice(&height.to_be_bytes());
+        jpeg.extend_from_slice(&width.to_be_bytes());
+        jpeg.extend_from_slice(&[0x01, 0x01, 0x11, 0x00]);
+        std::fs::write(path, jpeg).unwrap();
+    }
+
+    #[test]
+    fn test_validate() {
+        let root =
+            std::env::temp_dir().join(format!("manga109api_validate_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("annotations")).unwrap();
+        std::fs::create_dir_all(root.join("images/Good")).unwrap();
+        std::fs::create_dir_all(root.join("images/NoXml")).unwrap();
+        std::fs::write(root.join("books.txt"), "Good\nNoXml\n").unwrap();
+        std::fs::write(
+            root.join("annotations/Good.xml"),
+            r#"<book title="Good">
+                <characters>
+                    <character id="0000000a" name="Hero"/>
+                    <character id="0000000c" name="Twin"/>
+                    <character id="0000000c" name="Twin again"/>
+                </characters>
+                <pages>
+                <page index="0" width="100" height="200">
+                    <frame id="00000001" xmin="0" ymin="0" xmax="0" ymax="50"/>
+                    <face id="00000002" xmin="0" ymin="0" xmax="10" ymax="10" character="0000000a"/>
+                    <body id="00000002" xmin="0" ymin="0" xmax="10" ymax="10" character="0000000b"/>
+                    <text id="00000003" xmin="10" ymin="20" xmax="300" ymax="40">outside</text>
+                </page>
+                <page index="1" width="100" height="200"/>
+                <page index="2" width="100" height="200"/>
+            </pages></book>"#,
+        )
+        .unwrap();
+        write_jpeg_header(&root.join("images/Good/000.jpg"), 100, 200);
+        write_jpeg_header(&root.join("images/Good/001.jpg"), 200, 100);
+        write_jpeg_header(&root.join("images/Good/003.jpg"), 100, 200);
+        // not page images, so not unannotated ones either
+        std::fs::write(root.join("images/Good/.DS_Store"), "").unwrap();
+        std::fs::write(root.join("images/Good/Thumbs.db"), "").unwrap();
+        write_jpeg_header(&root.join("images/Good/preview.jpg"), 100, 200);
+
+        let issues = validate(root.to_str().unwrap(), &AnnotationVersion::Latest).unwrap();
+        let checks: Vec<(Check, &str, Option<usize>, Option<&str>)> = issues
+            .iter()
+            .map(|i| (i.check, i.title.as_str(), i.page, i.id.as_deref()))
+            .collect();
+        assert_eq!(
+            checks,
+            vec![
+                (Check::Defect, "Good", Some(0), Some("00000003")),
+                (Check::EmptyBox, "Good", Some(0), Some("00000001")),
+                (Check::UnknownCharacter, "Good", Some(0), Some("00000002")),
+                (Check::ImageSize, "Good", Some(1), None),
+                (Check::MissingImage, "Good", Some(2), None),
+                (Check::DuplicateId, "Good", Some(0), Some("00000002")),
+                (Check::DuplicateId, "Good", None, Some("0000000c")),
+                (Check::UnannotatedImage, "Good", None, None),
+                (Check::MissingAnnotationFile, "NoXml", None, None),
+            ]
+        );
+        assert!(!is_page_image_name("12.jpg"));
+        assert!(is_page_image_name("1000.jpg"));
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+
+// Manga109-s ships more than one release of the annotations side-by-side (see the readme that
+// comes with the dataset):
+//  - annotations/: annotations of the latest version (v2021.12.30)
+//  - annotations.v2020.12.18/: annotations of the old version
+//  - annotations.v2018.05.31/: annotations of the old version
+// This is the `annotation_type` argument of the Python manga109api, which is just the name of the
+// directory (under root_dir) to load the XML files from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum AnnotationVersion {
+    #[default]
+    Latest, // annotations/ (v2021.12.30)
+    V2020,          // annotations.v2020.12.18/
+    V2018,          // annotations.v2018.05.31/
+    Custom(String), // any other directory name relative to root_dir
+}
+
+impl AnnotationVersion {
+    pub fn dir_name(&self) -> &str {
+        match self {
+            AnnotationVersion::Latest => "annotations",
+            AnnotationVersion::V2020 => "annotations.v2020.12.18",
+            AnnotationVersion::V2018 => "annotations.v2018.05.31",
+            AnnotationVersion::Custom(dir_name) => dir_name,
+        }
+    }
+}
+
+impl std::str::FromStr for AnnotationVersion {
+    type Err = std::convert::Infallible;
+
+    // Accepts "latest", "v2021", "v2020", "v2018" (with or without the month/day, i.e. "v2018.05.31"),
+    // as well as the directory names themselves; anything else is taken as a custom directory name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "latest" | "annotations" | "v2021" | "v2021.12.30" => AnnotationVersion::Latest,
+            "v2020" | "v2020.12.18" | "annotations.v2020.12.18" => AnnotationVersion::V2020,
+            "v2018" | "v2018.05.31" | "annotations.v2018.05.31" => AnnotationVersion::V2018,
+            dir_name => AnnotationVersion::Custom(dir_name.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for AnnotationVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.dir_name())
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::{Body, Book, Error, Face, Frame, PageElement, Result, Text};
+
+// Writes Book back out in the same schema it was read from (see annotations.xsd), that is:
+//  <book title="...">
+//      <characters> <character id="..." name="..."/> ... </characters>
+//      <pages>
+//          <page index="..." width="..." height="...">
+//              <frame .../> <text ...>...</text> <face .../> <body .../>
+//          </page>
+//      </pages>
+//  </book>
+// Within a page, elements are written in the order they were read (see Page::elements()), so a
+// parsed book writes back with its elements in their original order.  ids are written back as-is,
+// so zero-padded hex ids such as "0000000b" stay zero-padded.
+// NOTE: a <text> with an empty value (only possible via ParseOptions::lenient()) is written as an
+// empty element, which strict parsing will (rightfully) reject again.
+
+const INDENT: &str = "  ";
+
+// escapes the 5 predefined XML entities, good for both attribute values and text content
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_rect(xml: &mut String, tag: &str, id: &str, rect: (u32, u32, u32, u32)) {
+    let (xmin, ymin, xmax, ymax) = rect;
+    // writing into a String never fails, hence the ignored fmt::Result here and below
+    let _ = write!(
+        xml,
+        "{0}{0}{0}<{1} id=\"{2}\" xmin=\"{3}\" ymin=\"{4}\" xmax=\"{5}\" ymax=\"{6}\"",
+        INDENT,
+        tag,
+        escape(id),
+        xmin,
+        ymin,
+        xmax,
+        ymax
+    );
+}
+
+fn write_frame(xml: &mut String, frame: &Frame) {
+    write_rect(
+        xml,
+        "frame",
+        &frame.id,
+        (frame.xmin, frame.ymin, frame.xmax, frame.ymax),
+    );
+    xml.push_str(" />\n");
+}
+
+fn write_text(xml: &mut String, text: &Text) {
+    write_rect(
+        xml,
+        "text",
+        &text.id,
+        (text.xmin, text.ymin, text.xmax, text.ymax),
+    );
+    if text.value.is_empty() {
+        xml.push_str(" />\n");
+    } else {
+        let _ = writeln!(xml, ">{}</text>", escape(&text.value));
+    }
+}
+
+fn write_face(xml: &mut String, face: &Face) {
+    write_rect(
+        xml,
+        "face",
+        &face.id,
+        (face.xmin, face.ymin, face.xmax, face.ymax),
+    );
+    let _ = writeln!(xml, " character=\"{}\" />", escape(&face.character));
+}
+
+fn write_body(xml: &mut String, body: &Body) {
+    write_rect(
+        xml,
+        "body",
+        &body.id,
+        (body.xmin, body.ymin, body.xmax, body.ymax),
+    );
+    let _ = writeln!(xml, " character=\"{}\" />", escape(&body.character));
+}
+
+impl Book {
+    pub fn to_xml_string(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        let _ = writeln!(xml, "<book title=\"{}\">", escape(&self.title));
+
+        let _ = writeln!(xml, "{}<characters>", INDENT);
+        for character in self.characters.iter() {
+            let _ = writeln!(
+                xml,
+                "{0}{0}<character id=\"{1}\" name=\"{2}\" />",
+                INDENT,
+                escape(&character.id),
+                escape(&character.name)
+            );
+        }
+        let _ = writeln!(xml, "{}</characters>", INDENT);
+
+        let _ = writeln!(xml, "{}<pages>", INDENT);
+        for page in self.pages.iter() {
+            let _ = write!(
+                xml,
+                "{0}{0}<page index=\"{1}\" width=\"{2}\" height=\"{3}\"",
+                INDENT, page.index, page.width, page.height
+            );
+            let elements = page.elements();
+            if elements.is_empty() {
+                xml.push_str(" />\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            for element in elements.iter() {
+                match element {
+                    PageElement::Frame(frame) => write_frame(&mut xml, frame),
+                    PageElement::Text(text) => write_text(&mut xml, text),
+                    PageElement::Face(face) => write_face(&mut xml, face),
+                    PageElement::Body(body) => write_body(&mut xml, body),
+                }
+            }
+            let _ = writeln!(xml, "{0}{0}</page>", INDENT);
+        }
+        let _ = writeln!(xml, "{}</pages>", INDENT);
+
+        xml.push_str("</book>\n");
+        xml
+    }
+
+    pub fn write_xml<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_xml_string()).map_err(|e| Error::Io {
+            path: path.to_path_buf(),
+            source: e,
+        })
+    }
+}
@@ -1,6 +1,5 @@
 use std::{fs::File, io::Write, path};
 
-use manga109api;
 // Why rusty_tesseract rather than tesseract?  Simple, because of the documentation.
 use rusty_tesseract::Args;
 
@@ -35,7 +34,20 @@ fn main() {
     let temp_image_paths = "./temp/ocr_rect.jpg";
     let report_csv = "./temp/ocr_report.csv";
     let manga109_root = "../../data/Manga109s/Manga109s_released_2023_12_07/";
-    let manga109 = manga109api::Manga109::new(manga109_root);
+    let manga109 =
+        manga109api::Manga109::new(manga109_root).expect("Unable to open Manga109 dataset");
+    // a book with broken annotations should not stop the rest of the books from being processed
+    let books: Vec<&manga109api::Book> = manga109
+        .load_all_results()
+        .into_iter()
+        .filter_map(|(title, result)| match result {
+            Ok(book) => Some(book),
+            Err(e) => {
+                println!("WARNING: Skipping book '{}': {}", title, e);
+                None
+            }
+        })
+        .collect();
 
     // overwrite existing CSV
     let mut csv_file: File = File::create(report_csv).expect("Failed to create CSV file");
@@ -47,41 +59,36 @@ fn main() {
     // For each book's page, locate each text box and create a temp image, then run tesseract on it
     // and CSV output the results in format of:
     //      book.title, page, text.id, text.characters.count, matched_character_count, missed_character_count, missed_characters.as_array()
-    for book in &manga109.books {
-        // iterate through each pages
-        for page in &book.pages {
-            // skip if this page has no text
-            if page.texts.len() == 0 {
-                continue;
-            }
-            // page has text, it's worth it to load the image (whole page)
-            let image_paths = manga109
-                .img_path(book.title.as_str(), &page.index)
-                .expect("Unable to get image path for {book.title}/{page.index}.jpg");
-            let image_page = rusty_tesseract::image::open(image_paths)
-                .expect("Unable to open image '{book.title}/{page.index}.jpg'");
-
-            // iterate through each text box
-            for text in &page.texts {
-                // create a temp image of the text box
-                let sub_image =
-                    image_page.crop_imm(text.xmin, text.ymin, text.get_width(), text.get_height());
-                // save it into a temp file so we can open it
-                sub_image
-                    .save(path::Path::new(temp_image_paths))
-                    .expect("Failed to save temp image");
-
-                // run tesseract on the text box
-                let temp_image = rusty_tesseract::image::open(temp_image_paths)
-                    .expect("Failed to run tesseract on text box");
-                let rusty_image = rusty_tesseract::Image::from_dynamic_image(&temp_image)
-                    .expect("Failed to convert image");
-                let ocr_result = rusty_tesseract::image_to_string(&rusty_image, &ocr_args)
-                    .expect("Failed to run tesseract on text box");
-
-                // compare the result with the ground truth
-                compare_ocr(&mut csv_file, &book.title, page.index, &text, &ocr_result);
-            }
+    // only the pages with text are worth it to load the image (whole page) of
+    let query = manga109api::Query::new(books);
+    for located in query
+        .pages()
+        .filter(|located| !located.page.texts.is_empty())
+    {
+        let (book, page) = (located.book, located.page);
+        let image_page = manga109
+            .load_page_image(book.title.as_str(), page.index)
+            .expect("Unable to open image '{book.title}/{page.index}.jpg'");
+
+        // iterate through each text box
+        for text in &page.texts {
+            // create a temp image of the text box
+            let sub_image = image_page.crop(text, 0);
+            // save it into a temp file so we can open it
+            sub_image
+                .save(path::Path::new(temp_image_paths))
+                .expect("Failed to save temp image");
+
+            // run tesseract on the text box
+            let temp_image = rusty_tesseract::image::open(temp_image_paths)
+                .expect("Failed to run tesseract on text box");
+            let rusty_image = rusty_tesseract::Image::from_dynamic_image(&temp_image)
+                .expect("Failed to convert image");
+            let ocr_result = rusty_tesseract::image_to_string(&rusty_image, &ocr_args)
+                .expect("Failed to run tesseract on text box");
+
+            // compare the result with the ground truth
+            compare_ocr(&mut csv_file, &book.title, page.index, text, &ocr_result);
         }
     }
     csv_file.flush().expect("Failed to flush CSV file");
@@ -97,16 +104,18 @@ fn main() {
 //      OCR Result: 'あの ば ぱか
 //      ウォ ー マ シ ン に
 //      ひび ひっかかり
-//      
+//
 //      や が っ た /。 .
 //      ' (97 chars)
 //      Matched: false
-//      
+//
 //      Expected: '超重子弾か！？' (21 chars)
 //      OCR Result: '超重 子 弾
 //      さっ 1
 //      ' (26 chars)
 //      Matched: false
+// count_diff, not_in_expected and found are not in the CSV yet, they are kept for the analysis
+#[allow(unused_variables, clippy::unnecessary_filter_map)]
 fn compare_ocr(
     writer: &mut File,
     title: &String,
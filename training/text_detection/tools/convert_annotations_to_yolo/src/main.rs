@@ -1,62 +1,131 @@
+use clap::Parser;
+use manga109api::{AnnotationType, HasBoundingBox};
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 use std::{io::Write, path};
 
+mod manifest;
+use manifest::{SplitBook, SplitManifest, MANIFEST_FILE_NAME};
+
+#[derive(Parser)]
+#[command(about = "Converts the Manga109 annotations into a YOLO (images/ + labels/) dataset")]
+struct Args {
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "../../../../data/Manga109s/Manga109s_released_2023_12_07/",
+        help = "Root of the Manga109 dataset (where books.txt is)"
+    )]
+    manga109_root: String,
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "../../data/",
+        help = "Where images/{train,val,test}/ and labels/{train,val,test}/ are created"
+    )]
+    output: String,
+    #[arg(long, default_value_t = 0.8, value_parser = parse_ratio, help = "Fraction of the books used for training")]
+    train: f32,
+    #[arg(long, default_value_t = 0.1, value_parser = parse_ratio, help = "Fraction of the books used for validation")]
+    val: f32,
+    // the ratios are relative to their sum, so --test need not make them add up to 1.0
+    #[arg(long, value_parser = parse_ratio, help = "Fraction of the books used for testing [default: 1 - train - val]")]
+    test: Option<f32>,
+    #[arg(
+        long,
+        help = "Seed of the shuffle of the books [default: random, see split.json]"
+    )]
+    seed: Option<u64>,
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "text",
+        help = "Annotation types to label, the class index of each is its position in the list"
+    )]
+    classes: Vec<Class>,
+    #[arg(
+        long,
+        value_name = "VERSION",
+        default_value = "latest",
+        help = "latest, v2020, v2018 or the name of the annotations directory"
+    )]
+    annotation_version: manga109api::AnnotationVersion,
+    #[arg(
+        long,
+        help = "Only print which books go to which split, nothing is written"
+    )]
+    dry_run: bool,
+    #[arg(
+        long,
+        value_name = "TITLES",
+        value_delimiter = ',',
+        help = "Titles to convert [default: every book in books.txt]"
+    )]
+    books: Vec<String>,
+    #[arg(
+        long,
+        value_name = "TITLES",
+        value_delimiter = ',',
+        help = "Titles to leave out"
+    )]
+    exclude_books: Vec<String>,
+    #[arg(
+        long,
+        help = "Parse the annotation XML files rather than reading the cache"
+    )]
+    no_cache: bool,
+    // everything which decides the split comes from the manifest
+    #[arg(
+        long,
+        value_name = "SPLIT_JSON",
+        conflicts_with_all = ["train", "val", "test", "seed", "classes", "annotation_version", "books", "exclude_books"],
+        help = "Convert exactly the books and pages of a split.json written by a previous run"
+    )]
+    from_manifest: Option<std::path::PathBuf>,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+enum Class {
+    Frame,
+    Text,
+    Face,
+    Body,
+}
+impl Class {
+    fn annotation_type(self) -> AnnotationType {
+        match self {
+            Class::Frame => AnnotationType::Frame,
+            Class::Text => AnnotationType::Text,
+            Class::Face => AnnotationType::Face,
+            Class::Body => AnnotationType::Body,
+        }
+    }
+}
+
+fn parse_ratio(arg: &str) -> Result<f32, String> {
+    let ratio: f32 = arg.parse().map_err(|e| format!("{}", e))?;
+    if !(0.0..=1.0).contains(&ratio) {
+        return Err(format!("{} is not in [0.0 .. 1.0]", ratio));
+    }
+    Ok(ratio)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum DatasetType {
     Train,
     Val,
     Test,
 }
 
-fn normalize_paths(path: &std::path::PathBuf) -> std::path::PathBuf {
+fn normalize_paths(path: &std::path::Path) -> std::path::PathBuf {
     let str_path = path.to_str().unwrap().replace("\\", "/").replace("//", "/");
     std::path::PathBuf::from(path::Path::new(str_path.as_str()))
 }
 
-// Coordinates are absolute image coordinates found in Manga109 annotations
-// Manga109 deals with rectangles that are assumes that origin of the image
-// is at upper-left corner of the image, making all rectangles have positive
-// coordinates.
-// The YOLO xywh format is normalized to range [0.0 .. 1.0] (note it's positive only)
-fn convert_boundingbox_to_yolo_xywh(
-    image_width: u32, // we don't care whether this dimension is of original image or resized image!
-    image_height: u32,
-    rect_minx: u32,
-    rect_miny: u32,
-    rect_maxx: u32,
-    rect_maxy: u32,
-) -> (
-    f32, /*center_x*/
-    f32, /*center_y*/
-    f32, /*width*/
-    f32, /*height*/
-) {
-    if image_width == 0 || image_height == 0 {
-        panic!("Image width and height must be positive");
-    }
-    if rect_minx >= rect_maxx || rect_miny >= rect_maxy {
-        panic!("Invalid bounding box");
-    }
-
-    let scale_x = 1.0 / image_width as f32;
-    let scale_y = 1.0 / image_height as f32;
-    let center_x = (rect_minx + rect_maxx) as f32 / 2.0;
-    let center_y = (rect_miny + rect_maxy) as f32 / 2.0;
-    let width = (rect_maxx - rect_minx) as f32;
-    let height = (rect_maxy - rect_miny) as f32;
-    let yolo_center_x = center_x * scale_x;
-    let yolo_center_y = center_y * scale_y;
-    let yolo_width = width * scale_x;
-    let yolo_height = height * scale_y;
-
-    if yolo_center_x < 0.0 || yolo_center_x > 1.0 || yolo_center_y < 0.0 || yolo_center_y > 1.0 {
-        panic!("Invalid YOLO center x/y");
-    }
-    if yolo_width < 0.0 || yolo_width > 1.0 || yolo_height < 0.0 || yolo_height > 1.0 {
-        panic!("Invalid YOLO width/height");
-    }
-    (yolo_center_x, yolo_center_y, yolo_width, yolo_height)
-}
-
 fn images_train_dir(root_data_dir: &str) -> std::path::PathBuf {
     std::path::Path::new(root_data_dir).join("images/train")
 }
@@ -75,14 +144,20 @@ fn images_test_dir(root_data_dir: &str) -> std::path::PathBuf {
 fn labels_test_dir(root_data_dir: &str) -> std::path::PathBuf {
     std::path::Path::new(root_data_dir).join("labels/test")
 }
-fn mk_dataset_dir(root_data_dir: &str) {
-    // 'mkdir -p' equivalent, if paths already exists, it will not panic
-    std::fs::create_dir_all(images_train_dir(root_data_dir)).unwrap();
-    std::fs::create_dir_all(images_val_dir(root_data_dir)).unwrap();
-    std::fs::create_dir_all(images_test_dir(root_data_dir)).unwrap();
-    std::fs::create_dir_all(labels_train_dir(root_data_dir)).unwrap();
-    std::fs::create_dir_all(labels_val_dir(root_data_dir)).unwrap();
-    std::fs::create_dir_all(labels_test_dir(root_data_dir)).unwrap();
+fn mk_dataset_dir(root_data_dir: &str) -> Result<(), String> {
+    // 'mkdir -p' equivalent, if paths already exists, it will not fail
+    for dir in [
+        images_train_dir(root_data_dir),
+        images_val_dir(root_data_dir),
+        images_test_dir(root_data_dir),
+        labels_train_dir(root_data_dir),
+        labels_val_dir(root_data_dir),
+        labels_test_dir(root_data_dir),
+    ] {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Unable to create '{:?}': {}", dir, e))?;
+    }
+    Ok(())
 }
 
 fn format_filename(title: &str, page: &usize, extension: &str) -> String {
@@ -94,14 +169,17 @@ fn copy_image_to_dataset(
     transformed_file_rootdir: &str,
     dstype: DatasetType,
     page: &usize,
-) {
+) -> Result<(), String> {
     //let src_annotation_file = std::path::Path::new(src_book.get_annotation_file_paths().as_str()); // i.e. annotations/title1.xml
     let img_dir_paths = src_book.get_image_dir_paths();
     let src_image_dir = std::path::Path::new(img_dir_paths.as_str()); // i.e.  images/{title}/
                                                                       // original/source is formatted as images/{title}/{page:03}.jpg
     let src_image_path = src_image_dir.join(format!("{:03}.jpg", page));
-    if src_image_path.exists() == false {
-        panic!("Source Image file not found: '{:?}'", src_image_path);
+    if !src_image_path.exists() {
+        return Err(format!(
+            "Source Image file not found: '{:?}'",
+            src_image_path
+        ));
     }
 
     let dest_image_dir = match dstype {
@@ -109,22 +187,23 @@ fn copy_image_to_dataset(
         DatasetType::Val => images_val_dir(transformed_file_rootdir),
         DatasetType::Test => images_test_dir(transformed_file_rootdir),
     };
-    let dest_label_dir = match dstype {
-        DatasetType::Train => labels_train_dir(transformed_file_rootdir),
-        DatasetType::Val => labels_val_dir(transformed_file_rootdir),
-        DatasetType::Test => labels_test_dir(transformed_file_rootdir),
-    };
     let dest_image_path = dest_image_dir.join(format_filename(&src_book.title, page, "jpg"));
     //let dest_label_path = dest_label_dir.join(format!("{}_{:03}.txt", title, page));
 
     // if dest image exists, we don't need to copy it again
-    if dest_image_path.exists() == false {
+    if !dest_image_path.exists() {
         println!(
             "Copying image from '{:?}' to '{:?}'",
             src_image_path, dest_image_path
         );
-        std::fs::copy(src_image_path, dest_image_path).unwrap();
+        std::fs::copy(&src_image_path, &dest_image_path).map_err(|e| {
+            format!(
+                "Unable to copy '{:?}' to '{:?}': {}",
+                src_image_path, dest_image_path, e
+            )
+        })?;
     }
+    Ok(())
 }
 
 fn get_labels_file_paths(
@@ -142,124 +221,393 @@ fn get_labels_file_paths(
     normalize_paths(&ret_path)
 }
 
+// A page goes to a single split, so if a previous run (with another split) left it in one of the
+// other splits, it is removed from there
+fn remove_from_other_splits(
+    transformed_file_rootdir: &str,
+    dstype: DatasetType,
+    title: &str,
+    page: &usize,
+) -> Result<(), String> {
+    for other in [DatasetType::Train, DatasetType::Val, DatasetType::Test] {
+        if other == dstype {
+            continue;
+        }
+        let image_dir = match other {
+            DatasetType::Train => images_train_dir(transformed_file_rootdir),
+            DatasetType::Val => images_val_dir(transformed_file_rootdir),
+            DatasetType::Test => images_test_dir(transformed_file_rootdir),
+        };
+        for stale_path in [
+            image_dir.join(format_filename(title, page, "jpg")),
+            get_labels_file_paths(transformed_file_rootdir, other, title, page),
+        ] {
+            if stale_path.exists() {
+                println!("Removing '{:?}' (now in {:?})", stale_path, dstype);
+                std::fs::remove_file(&stale_path)
+                    .map_err(|e| format!("Unable to remove '{:?}': {}", stale_path, e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
 // space seprated values: class_index center_x center_y width height
 fn write_yolo_label_file(
     dest_file: &mut std::fs::File,
+    class_index: usize,
     yolo_center_x: f32,
     yolo_center_y: f32,
     yolo_width: f32,
     yolo_height: f32,
-) {
+) -> std::io::Result<()> {
     let line = format!(
-        "0 {} {} {} {}\n",
-        yolo_center_x, yolo_center_y, yolo_width, yolo_height
+        "{} {} {} {} {}\n",
+        class_index, yolo_center_x, yolo_center_y, yolo_width, yolo_height
     );
-    dest_file.write_all(line.as_bytes()).unwrap();
+    dest_file.write_all(line.as_bytes())
 }
 
-fn main() {
-    let training_ratio = 0.8; // 80% training, 20% (validation and test)
-    let validation_ratio = 0.1; // 10% validation, 10% test
-    let transformed_file_rootdir = "../../data/";
-    let manga109_root = "../../../../data/Manga109s/Manga109s_released_2023_12_07/";
-    let manga109 = manga109api::Manga109::new(manga109_root);
-    mk_dataset_dir(transformed_file_rootdir);
+// Number of books in (train, val, test); the ratios need not add up to 1.0, they are relative to
+// their sum
+fn split_counts(num_books: usize, train: f32, val: f32, test: f32) -> (usize, usize, usize) {
+    let total = train + val + test;
+    let num_train = ((num_books as f32 * train / total).round() as usize).min(num_books);
+    let num_val = ((num_books as f32 * val / total).round() as usize).min(num_books - num_train);
+    (num_train, num_val, num_books - num_train - num_val)
+}
+
+// Titles to convert, in books.txt order
+fn select_titles(
+    titles: &[String],
+    books: &[String],
+    exclude_books: &[String],
+) -> Result<Vec<String>, String> {
+    if let Some(unknown) = books
+        .iter()
+        .chain(exclude_books)
+        .find(|title| !titles.contains(title))
+    {
+        return Err(format!("'{}' is not in books.txt", unknown));
+    }
+    Ok(titles
+        .iter()
+        .filter(|title| books.is_empty() || books.contains(title))
+        .filter(|title| !exclude_books.contains(title))
+        .cloned()
+        .collect())
+}
+
+// Labels of a page: the elements of the selected classes (in document order), with their class
+// index
+fn labels_of(
+    page: &manga109api::Page,
+    class_types: &[AnnotationType],
+) -> Vec<(usize, manga109api::PageElement)> {
+    page.elements()
+        .into_iter()
+        .filter_map(|element| {
+            let class_index = class_types.iter().position(|t| *t == element.kind())?;
+            Some((class_index, element))
+        })
+        .collect()
+}
 
+// Shuffles the books (with a ChaCha RNG, so that the same seed gives the same split on every
+// platform and rand version) and splits them by the ratios; each book lists its pages with at
+// least one label.  The books of each split are in books.txt order.
+fn make_manifest(
+    books: &[&manga109api::Book],
+    (train, val, test): (f32, f32, f32),
+    seed: u64,
+    classes: &[Class],
+    annotation_version: &manga109api::AnnotationVersion,
+) -> SplitManifest {
     // first, get number of books we have, and decide how many books to put in training and validation
-    let num_books = manga109.books.len();
-    let num_train_books = (num_books as f32 * training_ratio).round() as usize;
-    let num_val_books = (num_books as f32 * validation_ratio).round() as usize;
-    let num_test_books = num_books - num_train_books - num_val_books;
+    let num_books = books.len();
+    let (num_train_books, num_val_books, _num_test_books) =
+        split_counts(num_books, train, val, test);
 
     // shuffle book indices to randomly select books for training and validation
     let mut book_indices: Vec<usize> = (0..num_books).collect();
-    book_indices.shuffle(&mut rand::thread_rng());
+    book_indices.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
     let train_books_indices = &book_indices[0..num_train_books];
-    let val_books_indices = &book_indices[num_train_books..num_books];
+    let val_books_indices = &book_indices[num_train_books..num_train_books + num_val_books];
     let test_books_indices = &book_indices[num_train_books + num_val_books..num_books];
 
-    // iterate through each books while converting annotations to YOLO format
-    for (book_index, book) in manga109.books.iter().enumerate() {
-        let is_training_dataset = train_books_indices.contains(&book_index);
-        let is_validation_dataset = val_books_indices.contains(&book_index);
+    let class_types: Vec<AnnotationType> = classes.iter().map(|c| c.annotation_type()).collect();
+    let split_books = |indices: &[usize]| {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .map(|book_index| SplitBook {
+                title: books[book_index].title.clone(),
+                pages: books[book_index]
+                    .pages
+                    .iter()
+                    .filter(|page| !labels_of(page, &class_types).is_empty())
+                    .map(|page| page.index)
+                    .collect(),
+            })
+            .collect()
+    };
+    SplitManifest {
+        seed,
+        annotation_version: annotation_version.dir_name().to_string(),
+        classes: classes.to_vec(),
+        train: split_books(train_books_indices),
+        val: split_books(val_books_indices),
+        test: split_books(test_books_indices),
+    }
+}
 
-        for page in &book.pages {
-            // skip pages that has no TEXT
-            if page.texts.len() == 0 {
-                continue;
-            }
+// Copies the images and writes the label files of every page of the manifest, then the manifest
+// itself
+fn materialize(
+    manga109: &manga109api::Manga109,
+    manifest: &SplitManifest,
+    transformed_file_rootdir: &str,
+) -> Result<(), String> {
+    mk_dataset_dir(transformed_file_rootdir)?;
+    let class_types: Vec<AnnotationType> = manifest
+        .classes
+        .iter()
+        .map(|class| class.annotation_type())
+        .collect();
+    for dataset_type in [DatasetType::Train, DatasetType::Val, DatasetType::Test] {
+        for split_book in manifest.split(dataset_type) {
+            let book = manga109
+                .book(&split_book.title)
+                .map_err(|e| format!("Unable to load book '{}': {}", split_book.title, e))?;
+            for page_index in split_book.pages.iter() {
+                let page = book
+                    .page(*page_index)
+                    .ok_or_else(|| format!("'{}' has no page {}", split_book.title, page_index))?;
 
-            // writer based on whether book_index is in train_books_indices or val_books_indices
-            let mut writer_per_page = if is_training_dataset {
-                copy_image_to_dataset(
-                    book,
+                // copy the page image and create its label file in the split the book belongs to
+                remove_from_other_splits(
                     transformed_file_rootdir,
-                    DatasetType::Train,
+                    dataset_type,
+                    &book.title,
                     &page.index,
-                );
-
+                )?;
+                copy_image_to_dataset(book, transformed_file_rootdir, dataset_type, &page.index)?;
                 let path = get_labels_file_paths(
                     transformed_file_rootdir,
-                    DatasetType::Train,
+                    dataset_type,
                     &book.title.clone(),
                     &page.index,
                 );
                 println!("Writing to file: '{:?}'", path);
-                std::fs::File::create(path).unwrap()
-            } else if is_validation_dataset {
-                copy_image_to_dataset(
-                    book,
-                    transformed_file_rootdir,
-                    DatasetType::Val,
-                    &page.index,
-                );
+                let mut writer_per_page = std::fs::File::create(&path)
+                    .map_err(|e| format!("Unable to create '{:?}': {}", path, e))?;
 
-                let path = get_labels_file_paths(
-                    transformed_file_rootdir,
-                    DatasetType::Val,
-                    &book.title.clone(),
-                    &page.index,
-                );
-                println!("Writing to file: '{:?}'", path);
-                std::fs::File::create(path).unwrap()
-            } else {
-                copy_image_to_dataset(
-                    book,
-                    transformed_file_rootdir,
-                    DatasetType::Test,
-                    &page.index,
-                );
+                for (class_index, element) in labels_of(page, &class_types) {
+                    // NOTE: We only care about the rectangle coordinates, not the text (value) itself...
+                    // YOLO xywh is normalized to [0.0 .. 1.0], so keep the box on the page
+                    let bbox = element.bounding_box().clamp(page.width, page.height);
+                    if bbox.is_empty() {
+                        println!(
+                            "WARNING: Skipping empty {} box '{}' of '{}' page {}",
+                            element.kind().tag(),
+                            element.id(),
+                            book.title,
+                            page.index
+                        );
+                        continue;
+                    }
+                    // a page without width/height has clamped every box to empty already
+                    let Some((yolo_center_x, yolo_center_y, yolo_width, yolo_height)) =
+                        bbox.to_xywh_normalized(page.width, page.height)
+                    else {
+                        continue;
+                    };
+                    write_yolo_label_file(
+                        &mut writer_per_page,
+                        class_index,
+                        yolo_center_x,
+                        yolo_center_y,
+                        yolo_width,
+                        yolo_height,
+                    )
+                    .map_err(|e| format!("Unable to write '{:?}': {}", path, e))?;
+                }
+                writer_per_page
+                    .flush()
+                    .map_err(|e| format!("Unable to write '{:?}': {}", path, e))?;
+                // close?
+            }
+        }
+    }
+    manifest.write(&std::path::Path::new(transformed_file_rootdir).join(MANIFEST_FILE_NAME))
+}
 
-                let path = get_labels_file_paths(
-                    transformed_file_rootdir,
-                    DatasetType::Test,
-                    &book.title.clone(),
-                    &page.index,
-                );
-                println!("Writing to file: '{:?}'", path);
-                std::fs::File::create(path).unwrap()
-            };
+fn main() {
+    let args = Args::parse();
+    let from_manifest = args
+        .from_manifest
+        .as_ref()
+        .map(|path| SplitManifest::read(path).unwrap_or_else(|e| exit_with_error(&e)));
+    let annotation_version = match &from_manifest {
+        Some(manifest) => manifest.annotation_version.parse().unwrap_or_default(),
+        None => args.annotation_version.clone(),
+    };
 
-            for text in &page.texts {
-                // NOTE: We only care about the rectangle coordinates of the text, not the text (value) itself...
-                let (yolo_center_x, yolo_center_y, yolo_width, yolo_height) =
-                    convert_boundingbox_to_yolo_xywh(
-                        page.width,
-                        page.height,
-                        text.xmin,
-                        text.ymin,
-                        text.xmax,
-                        text.ymax,
-                    );
-                write_yolo_label_file(
-                    &mut writer_per_page,
-                    yolo_center_x,
-                    yolo_center_y,
-                    yolo_width,
-                    yolo_height,
-                );
+    let manga109 = manga109api::Manga109::new_with_version(
+        &args.manga109_root,
+        &annotation_version,
+        &manga109api::ParseOptions::default(),
+    )
+    .unwrap_or_else(|e| exit_with_error(&format!("Unable to open Manga109 dataset: {}", e)));
+    // a dry run writes nothing at all, not even the cache of the parsed books
+    let manga109 = if args.no_cache || args.dry_run {
+        manga109.without_cache()
+    } else {
+        manga109
+    };
+
+    let manifest = match from_manifest {
+        Some(manifest) => manifest,
+        None => {
+            let test_ratio = match args.test {
+                Some(test) => test,
+                None if args.train + args.val <= 1.0 => 1.0 - args.train - args.val,
+                None => exit_with_error("--train and --val add up to more than 1.0"),
+            };
+            if args.train + args.val + test_ratio <= 0.0 {
+                exit_with_error("--train, --val and --test are all 0.0");
+            }
+            let titles = select_titles(manga109.titles(), &args.books, &args.exclude_books)
+                .unwrap_or_else(|e| exit_with_error(&e));
+            // the split of a seed depends on which books are shuffled, so a book which fails to
+            // load (on this machine) is an error rather than silently left out; --exclude-books it
+            // to go without it
+            let mut books: Vec<&manga109api::Book> = Vec::new();
+            let mut failed = Vec::new();
+            for title in titles.iter() {
+                match manga109.book(title) {
+                    Ok(book) => books.push(book),
+                    Err(e) => {
+                        eprintln!("Unable to load book '{}': {}", title, e);
+                        failed.push(title.as_str());
+                    }
+                }
             }
-            writer_per_page.flush().unwrap(); // close?
+            if !failed.is_empty() {
+                exit_with_error(&format!(
+                    "{} book(s) failed to load, leave them out with --exclude-books {}",
+                    failed.len(),
+                    failed.join(",")
+                ));
+            }
+            // without --seed, a random one (which goes into the manifest, so the split can still
+            // be reproduced)
+            let seed = args.seed.unwrap_or_else(rand::random);
+            make_manifest(
+                &books,
+                (args.train, args.val, test_ratio),
+                seed,
+                &args.classes,
+                &annotation_version,
+            )
         }
+    };
+
+    println!("Seed: {}", manifest.seed);
+    for dataset_type in [DatasetType::Train, DatasetType::Val, DatasetType::Test] {
+        let split = manifest.split(dataset_type);
+        let titles: Vec<&str> = split.iter().map(|book| book.title.as_str()).collect();
+        println!(
+            "{:?} ({} books, {} pages): {}",
+            dataset_type,
+            split.len(),
+            split.iter().map(|book| book.pages.len()).sum::<usize>(),
+            titles.join(", ")
+        );
+    }
+    if args.dry_run {
+        return;
+    }
+    materialize(&manga109, &manifest, &args.output).unwrap_or_else(|e| exit_with_error(&e));
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_counts() {
+        assert_eq!(split_counts(109, 0.8, 0.1, 0.1), (87, 11, 11));
+        assert_eq!(split_counts(10, 8.0, 1.0, 1.0), (8, 1, 1));
+        assert_eq!(split_counts(3, 0.8, 0.1, 0.1), (2, 0, 1));
+        assert_eq!(split_counts(1, 0.5, 0.5, 0.0), (1, 0, 0));
+    }
+
+    #[test]
+    fn test_select_titles() {
+        let titles: Vec<String> = ["A", "B", "C"].iter().map(|t| t.to_string()).collect();
+        let list = |l: &[&str]| l.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert_eq!(select_titles(&titles, &[], &[]).unwrap(), titles);
+        assert_eq!(
+            select_titles(&titles, &list(&["C", "A"]), &list(&["A"])).unwrap(),
+            list(&["C"])
+        );
+        assert!(select_titles(&titles, &list(&["D"]), &[]).is_err());
+    }
+
+    #[test]
+    fn test_manifest() {
+        let root = std::env::temp_dir().join(format!("yolo_manifest_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let options = manga109api::fixture::FixtureOptions {
+            books: 6,
+            pages_per_book: 2,
+            ..Default::default()
+        };
+        manga109api::fixture::generate(root.join("manga109"), &options).unwrap();
+        let manga109 = manga109api::Manga109::new(root.join("manga109").to_str().unwrap())
+            .unwrap()
+            .without_cache();
+        let books = manga109.load_all().unwrap();
+        let version = manga109api::AnnotationVersion::Latest;
+        let make = |seed| make_manifest(&books, (0.5, 0.25, 0.25), seed, &[Class::Text], &version);
+
+        // the same seed, the same split
+        let manifest = make(109);
+        assert_eq!(make(109), manifest);
+        let split_sizes = |m: &SplitManifest| (m.train.len(), m.val.len(), m.test.len());
+        assert_eq!(split_sizes(&manifest), (3, 2, 1));
+        assert!((0..8).any(|seed| make(seed) != manifest));
+
+        // materialized, then read back from split.json
+        let output = root.join("yolo");
+        materialize(&manga109, &manifest, output.to_str().unwrap()).unwrap();
+        let written = SplitManifest::read(&output.join(MANIFEST_FILE_NAME)).unwrap();
+        assert_eq!(written, manifest);
+        let book = &manifest.val[0];
+        let label = get_labels_file_paths(
+            output.to_str().unwrap(),
+            DatasetType::Val,
+            &book.title,
+            &book.pages[0],
+        );
+        assert!(std::fs::read_to_string(label).unwrap().starts_with("0 "));
+
+        // a missing page image is an error, not a panic
+        let book = &manifest.train[0];
+        let image = root
+            .join("manga109/images")
+            .join(&book.title)
+            .join(format!("{:03}.jpg", book.pages[0]));
+        std::fs::remove_file(image).unwrap();
+        let error = materialize(&manga109, &manifest, root.join("yolo2").to_str().unwrap());
+        assert!(error.unwrap_err().contains("not found"));
+        let _ = std::fs::remove_dir_all(&root);
     }
 }
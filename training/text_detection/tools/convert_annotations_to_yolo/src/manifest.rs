@@ -0,0 +1,55 @@
+// split.json, written next to images/ and labels/, records which pages of which books went to
+// which split, so that a split (and the evaluation of a model trained on it) can be reproduced
+// with --from-manifest:
+//  {
+//    "seed": 109,
+//    "annotation_version": "annotations",
+//    "classes": ["text"],
+//    "train": [{"title": "ARMS", "pages": [3, 4, ...]}, ...],
+//    "val": [...],
+//    "test": [...]
+//  }
+// The paths (Manga109 root and output directory) are machine specific, so they are not in it.
+use serde::{Deserialize, Serialize};
+
+use crate::{Class, DatasetType};
+
+pub const MANIFEST_FILE_NAME: &str = "split.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitManifest {
+    pub seed: u64,
+    pub annotation_version: String, // AnnotationVersion::dir_name()
+    pub classes: Vec<Class>,        // class index is the position in this list
+    pub train: Vec<SplitBook>,
+    pub val: Vec<SplitBook>,
+    pub test: Vec<SplitBook>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitBook {
+    pub title: String,
+    pub pages: Vec<usize>, // Page::index of the converted pages
+}
+
+impl SplitManifest {
+    pub fn split(&self, dataset_type: DatasetType) -> &[SplitBook] {
+        match dataset_type {
+            DatasetType::Train => &self.train,
+            DatasetType::Val => &self.val,
+            DatasetType::Test => &self.test,
+        }
+    }
+
+    pub fn read(path: &std::path::Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read '{}': {}", path.display(), e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Invalid '{}': {}", path.display(), e))
+    }
+
+    pub fn write(&self, path: &std::path::Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        std::fs::write(path, json + "\n")
+            .map_err(|e| format!("Unable to write '{}': {}", path.display(), e))
+    }
+}